@@ -1,23 +1,77 @@
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet, VecDeque};
+use std::iter::once;
+use std::ops::Range;
 use tested_trait::{test_impl, tested_trait};
 
-use crate::{CommitState, Runners, State, Status};
+use crate::{
+    flaky::{condition_on_known, Flaky},
+    CommitState, Runners, State, Status,
+};
 
 // I'm pretty sure this is optimal for all common cases
 // There might be a better allocation by doubling-up on certain commits if somehow variance is very high and mean is low. Seems unlikely.
-fn initial_alloc(commits: &[String], runners: usize, check_bookends: bool) -> Vec<usize> {
-    let mut runners_to_allocate = runners;
+fn initial_alloc(commits: usize, runners: usize, check_bookends: bool) -> Vec<usize> {
+    initial_around_bookends(
+        commits,
+        runners,
+        check_bookends,
+        |_, lower_bound, upper_bound, runners_to_allocate| {
+            if runners_to_allocate == 0 {
+                return vec![];
+            }
+            let spacing = (upper_bound - lower_bound) / (runners_to_allocate + 1);
+
+            (0..runners_to_allocate)
+                .map(|x| x * spacing + spacing + lower_bound)
+                .collect()
+        },
+    )
+}
 
-    if commits.len() <= runners_to_allocate {
-        return (0..commits.len()).collect();
+/// Allocate the initial runners: every commit if there are no more commits than runners, otherwise the bookends if
+/// requested and wherever `place` puts the rest. `place` gets the bookend runners, the remaining inclusive bounds and
+/// the # of runners left to allocate. Runners it places twice or on a bookend are only allocated once.
+fn initial_around_bookends<P>(
+    commits: usize,
+    runners: usize,
+    check_bookends: bool,
+    place: P,
+) -> Vec<usize>
+where
+    P: FnOnce(&HashSet<usize>, usize, usize, usize) -> Vec<usize>,
+{
+    if commits <= runners {
+        return (0..commits).collect();
     }
 
-    if runners_to_allocate == 0 {
+    if runners == 0 {
         return vec![];
     }
 
+    let (bookends, lower_bound, upper_bound, runners_to_allocate) =
+        initial_bookends(commits, runners, check_bookends);
+    let placed = place(&bookends, lower_bound, upper_bound, runners_to_allocate);
+
+    let mut new_runners = bookends.into_iter().collect::<Vec<_>>();
+    for runner in placed {
+        if !new_runners.contains(&runner) {
+            new_runners.push(runner);
+        }
+    }
+    new_runners
+}
+
+/// Schedule the bookends of the initial bisection range if requested.
+/// Returns the bookend runners, the remaining inclusive bounds and the # of runners left to allocate.
+fn initial_bookends(
+    commits: usize,
+    runners: usize,
+    check_bookends: bool,
+) -> (HashSet<usize>, usize, usize, usize) {
+    let mut runners_to_allocate = runners;
+
     let mut lower_bound = 0;
-    let mut upper_bound = commits.len() - 1;
+    let mut upper_bound = commits - 1;
 
     let mut new_runners = HashSet::new();
 
@@ -35,34 +89,554 @@ fn initial_alloc(commits: &[String], runners: usize, check_bookends: bool) -> Ve
         }
     }
 
-    if runners_to_allocate != 0 {
-        let spacing = (upper_bound - lower_bound) / (runners_to_allocate + 1);
+    (new_runners, lower_bound, upper_bound, runners_to_allocate)
+}
+
+/// Allocate free runners over the bisection range: every commit that can be tested if there are no more commits than
+/// runners, otherwise the oldest commit first if it's a bookend to check, and wherever `place` puts the rest. `place`
+/// gets the range left to place runners in, the # of runners left to allocate and which commits can be tested.
+/// Runners it places twice are only allocated once.
+fn alloc_around_bookends<P>(
+    runners: usize,
+    existing_alloc: &[usize],
+    bisection_range: (usize, &[CommitState]),
+    check_bookends: bool,
+    place: P,
+) -> Vec<usize>
+where
+    P: FnOnce(Range<usize>, usize, &dyn Fn(usize) -> bool) -> Vec<usize>,
+{
+    let (start, range) = bisection_range;
+    let testable =
+        |x: usize| !existing_alloc.contains(&x) && range[x - start].status == Status::Unknown;
+
+    if range.len() <= runners {
+        // We can allocate everything!
+        return (start..start + range.len())
+            .filter(|x| testable(*x))
+            .collect();
+    }
+
+    let mut new_runners = vec![];
+    let mut lower = start;
+    let mut count = runners.saturating_sub(existing_alloc.len());
+
+    // If runners are >= 2, then the bounds would already be scheduled.
+    if check_bookends && runners == 1 && start == 0 && count != 0 {
+        new_runners.push(0);
+        lower += 1;
+        count -= 1;
+    }
+
+    for runner in place(lower..start + range.len(), count, &testable) {
+        if !new_runners.contains(&runner) {
+            new_runners.push(runner);
+        }
+    }
+    new_runners
+}
+
+/// Smallest prior mass a commit can have. Keeps zero-weighted commits testable so the bisection always terminates.
+const MIN_PRIOR_MASS: f64 = 1e-12;
+
+pub fn prior_mass(commit: &CommitState) -> f64 {
+    commit.weight.max(MIN_PRIOR_MASS)
+}
+
+/// Commits in the shadow of skipped commits, which are likely to be untestable as well.
+/// A run of skipped commits shadows as many commits on either side of it as it is long.
+/// Neighbours are by index, which in a history with merges are mostly parents and children.
+pub fn skip_shadow(commits: &[CommitState]) -> Vec<bool> {
+    let mut shadow = vec![false; commits.len()];
+    let mut start = 0;
+    for run in commits.chunk_by(|a, b| (a.status == Status::Skip) == (b.status == Status::Skip)) {
+        if run[0].status == Status::Skip {
+            let end = start + run.len();
+            for x in start.saturating_sub(run.len())..(end + run.len()).min(commits.len()) {
+                shadow[x] |= commits[x].status == Status::Unknown;
+            }
+        }
+        start += run.len();
+    }
+    shadow
+}
+
+/// Share of the information a runner in the shadow of skipped commits is expected to yield, as it's likely skipped too.
+const SHADOW_YIELD: f64 = 0.5;
+
+/// Share of the information a runner on a commit is expected to yield. None if it can't be tested.
+fn information_yield(testable: bool, shadowed: bool) -> f64 {
+    match (testable, shadowed) {
+        (false, _) => 0.0,
+        (true, true) => SHADOW_YIELD,
+        (true, false) => 1.0,
+    }
+}
+
+/// The `testable` commit in `candidates` to place a runner on in place of `target`.
+/// Commits in the shadow of skipped commits count as `reach + 1` commits further away than they are,
+/// so a clear commit up to `reach` commits further away is picked over them.
+fn nearest_clear<T, S>(
+    candidates: Range<usize>,
+    target: usize,
+    reach: usize,
+    testable: T,
+    shadowed: S,
+) -> Option<usize>
+where
+    T: Fn(usize) -> bool,
+    S: Fn(usize) -> bool,
+{
+    candidates
+        .filter(|x| testable(*x))
+        .min_by_key(|x| x.abs_diff(target) + if shadowed(*x) { reach + 1 } else { 0 })
+}
+
+/// Pick `count` indexes from `candidates` that split their prior mass into `count + 1` equal parts.
+/// May return duplicates if a single commit holds a large share of the mass.
+fn mass_quantiles(candidates: &[(usize, f64)], count: usize) -> Vec<usize> {
+    let total: f64 = candidates.iter().map(|(_, mass)| mass).sum();
+
+    let mut quantiles = vec![];
+    let mut cumulative = 0.0;
+    let mut candidates = candidates.iter();
+    let mut last = None;
+    for q in 1..=count {
+        let target = total * q as f64 / (count + 1) as f64;
+        while cumulative < target {
+            match candidates.next() {
+                Some((idx, mass)) => {
+                    cumulative += mass;
+                    last = Some(*idx);
+                }
+                None => break,
+            }
+        }
+        quantiles.extend(last);
+    }
+
+    quantiles
+}
+
+fn plogp(p: f64) -> f64 {
+    if p > 0.0 {
+        p * p.ln()
+    } else {
+        0.0
+    }
+}
+
+/// Greedily pick `count` new cuts that maximize the entropy of the partition the cuts induce on `masses`.
+/// A cut at `i` separates `..=i` from `i + 1..`, just like testing commit `i` separates Bad from Good.
+/// The information of a cut is scaled by its `yields`, see [`information_yield`], so only testable indexes that aren't
+/// already in `cuts` are picked. Stops early once no cut gains information.
+fn information_cuts(masses: &[f64], yields: &[f64], cuts: &[usize], count: usize) -> Vec<usize> {
+    let prefix = once(0.0)
+        .chain(masses.iter().scan(0.0, |acc, mass| {
+            *acc += mass;
+            Some(*acc)
+        }))
+        .collect::<Vec<_>>();
+
+    let mut cuts = cuts.iter().copied().collect::<BTreeSet<_>>();
+    let mut new_cuts = vec![];
+
+    for _ in 0..count {
+        let mut best: Option<(usize, f64)> = None;
+
+        // Every segment between existing cuts is one possible outcome. Splitting it gains
+        // H(segment) - H(left) - H(right), scaled by the segment's share of the mass.
+        let mut lower = 0;
+        for upper in cuts.iter().map(|cut| cut + 1).chain(once(masses.len())) {
+            let segment = prefix[upper] - prefix[lower];
+            for cut in (lower..upper).filter(|x| !cuts.contains(x)) {
+                let left = prefix[cut + 1] - prefix[lower];
+                let right = segment - left;
+                let gain = yields[cut] * (plogp(segment) - plogp(left) - plogp(right));
+
+                // Ties go to the newer commit, like the BasicAllocator's spacing
+                if gain > 0.0 && best.is_none_or(|(_, best_gain)| gain >= best_gain) {
+                    best = Some((cut, gain));
+                }
+            }
+            lower = upper;
+        }
+
+        match best {
+            Some((cut, _)) => {
+                cuts.insert(cut);
+                new_cuts.push(cut);
+            }
+            None => break,
+        }
+    }
+
+    new_cuts
+}
+
+/// Most commits whose split is evaluated per runner in a history with merges.
+/// Evaluating one walks all of its ancestors, so huge ranges only look at an evenly spaced sample.
+const DAG_PROBES: usize = 256;
+
+/// Positions in `suspects` of `commit` and its ancestors among them.
+/// Ancestors of a suspect are either suspects or known good, so the walk stops at the first commit that isn't one.
+fn suspect_ancestors(
+    commits: &[CommitState],
+    position: &[Option<usize>],
+    visited: &mut [usize],
+    generation: usize,
+    commit: usize,
+) -> Vec<usize> {
+    let mut ancestors = vec![];
+    let mut stack = vec![commit];
+    while let Some(x) = stack.pop() {
+        if visited[x] == generation {
+            continue;
+        }
+        visited[x] = generation;
+        if let Some(pos) = position[x] {
+            ancestors.push(pos);
+            stack.extend(commits[x].parents.iter());
+        }
+    }
+    ancestors
+}
+
+/// Groups of suspects that the results of a set of runners can't tell apart.
+struct Partition {
+    /// Mass of every suspect
+    masses: Vec<f64>,
+    /// Group of every suspect
+    group: Vec<usize>,
+    group_mass: Vec<f64>,
+    group_size: Vec<usize>,
+}
+
+impl Partition {
+    fn new(masses: Vec<f64>) -> Self {
+        Partition {
+            group: vec![0; masses.len()],
+            group_mass: vec![masses.iter().sum()],
+            group_size: vec![masses.len()],
+            masses,
+        }
+    }
+
+    /// Information gained by a result that tells `ancestors` apart from the other suspects.
+    /// Splitting a group gains H(group) - H(ancestors in it) - H(the rest of it).
+    fn gain(&self, ancestors: &[usize]) -> f64 {
+        let mut inside_mass = vec![0.0; self.group_mass.len()];
+        let mut inside_size = vec![0; self.group_mass.len()];
+        for pos in ancestors {
+            inside_mass[self.group[*pos]] += self.masses[*pos];
+            inside_size[self.group[*pos]] += 1;
+        }
+
+        (0..self.group_mass.len())
+            .filter(|g| inside_size[*g] != 0 && inside_size[*g] != self.group_size[*g])
+            .map(|g| {
+                plogp(self.group_mass[g])
+                    - plogp(inside_mass[g])
+                    - plogp(self.group_mass[g] - inside_mass[g])
+            })
+            .sum()
+    }
+
+    /// Tell `ancestors` apart from the other suspects.
+    fn split(&mut self, ancestors: &[usize]) {
+        let mut inside = vec![0; self.group_mass.len()];
+        for pos in ancestors {
+            inside[self.group[*pos]] += 1;
+        }
+
+        let mut split_off: Vec<Option<usize>> = vec![None; self.group_mass.len()];
+        for pos in ancestors {
+            let old = self.group[*pos];
+            if inside[old] == self.group_size[old] {
+                continue;
+            }
+            let new = *split_off[old].get_or_insert_with(|| {
+                self.group_mass.push(0.0);
+                self.group_size.push(0);
+                self.group_mass.len() - 1
+            });
+            self.group[*pos] = new;
+            self.group_mass[old] -= self.masses[*pos];
+            self.group_mass[new] += self.masses[*pos];
+            self.group_size[old] -= 1;
+            self.group_size[new] += 1;
+        }
+    }
+}
+
+/// Greedily pick new runners whose results split the `suspects` of a history with merges into parts of even `mass`.
+/// A bad result leaves the ancestors of the tested commit, a good result everything else.
+/// Results of runners in `existing_alloc` are counted as if they were known. Stops early once no commit gains information.
+/// Commits in the shadow of skipped commits yield less, see [`information_yield`].
+fn dag_cuts<M>(
+    runners: usize,
+    existing_alloc: &[usize],
+    commits: &[CommitState],
+    suspects: &[usize],
+    check_bookends: bool,
+    mass: M,
+) -> Vec<usize>
+where
+    M: Fn(&CommitState) -> f64,
+{
+    let count = runners.saturating_sub(existing_alloc.len());
+    let mut new_runners = vec![];
+    if count == 0 || commits.is_empty() {
+        return new_runners;
+    }
+
+    let mut position = vec![None; commits.len()];
+    for (pos, x) in suspects.iter().enumerate() {
+        position[*x] = Some(pos);
+    }
+    let untested = |x: usize| commits[x].status == Status::Unknown && !existing_alloc.contains(&x);
+    let shadow = skip_shadow(commits);
+
+    // Unvalidated bookends go first, the bad one before the good one like in `initial_bookends`
+    if check_bookends {
+        for bookend in [commits.len() - 1, 0] {
+            if new_runners.len() < count
+                && position[bookend].is_some()
+                && untested(bookend)
+                && !new_runners.contains(&bookend)
+            {
+                new_runners.push(bookend);
+            }
+        }
+    }
+
+    let mut partition = Partition::new(suspects.iter().map(|x| mass(&commits[*x])).collect());
+    let mut visited = vec![0; commits.len()];
+    let mut generation = 0;
+    let mut ancestors = |commit: usize| {
+        generation += 1;
+        suspect_ancestors(commits, &position, &mut visited, generation, commit)
+    };
+
+    for x in existing_alloc.iter().chain(&new_runners) {
+        if position[*x].is_some() {
+            partition.split(&ancestors(*x));
+        }
+    }
+
+    let testable = suspects
+        .iter()
+        .copied()
+        .filter(|x| untested(*x) && !new_runners.contains(x))
+        .collect::<Vec<_>>();
+    let probes = if testable.len() <= DAG_PROBES {
+        testable
+    } else {
+        (0..DAG_PROBES)
+            .map(|i| testable[i * testable.len() / DAG_PROBES])
+            .collect()
+    };
+
+    while new_runners.len() < count {
+        let mut best: Option<(usize, f64, Vec<usize>)> = None;
+
+        for probe in probes.iter().filter(|x| !new_runners.contains(x)) {
+            let probe_ancestors = ancestors(*probe);
+            let gain = information_yield(true, shadow[*probe]) * partition.gain(&probe_ancestors);
+
+            // Ties go to the newer commit, like in `information_cuts`
+            if gain > 0.0
+                && best
+                    .as_ref()
+                    .is_none_or(|(_, best_gain, _)| gain >= *best_gain)
+            {
+                best = Some((*probe, gain, probe_ancestors));
+            }
+        }
+
+        match best {
+            Some((probe, _, probe_ancestors)) => {
+                partition.split(&probe_ancestors);
+                new_runners.push(probe);
+            }
+            None => break,
+        }
+    }
+
+    new_runners
+}
+
+/// Normalize commit weights into prior probabilities.
+fn normalize_prior(commits: &mut [CommitState]) {
+    let commit_count = commits.len();
+    let total_weight: f64 = commits.iter().map(|c| c.weight).sum();
+    for commit in commits.iter_mut() {
+        commit.weight = if total_weight > 0.0 {
+            commit.weight / total_weight
+        } else {
+            // Everything was weighted down to zero. Fall back to a uniform prior.
+            1.0 / commit_count as f64
+        };
+    }
+}
+
+/// Place runners where their results tell the most about the first bad commit, given the weights so far.
+/// Commits may be tested again once their last runner is done. Runners in `existing_alloc` count as placed.
+fn flaky_alloc(commits: &[CommitState], runners: usize, existing_alloc: &[usize]) -> Vec<usize> {
+    let count = runners.saturating_sub(existing_alloc.len());
 
-        new_runners.extend((0..runners_to_allocate).map(|x| x * spacing + spacing + lower_bound))
+    if !is_linear(commits) {
+        let all = (0..commits.len()).collect::<Vec<_>>();
+        return dag_cuts(runners, existing_alloc, commits, &all, false, |x| x.weight);
     }
 
-    new_runners.into_iter().collect()
+    let masses = commits.iter().map(|x| x.weight).collect::<Vec<_>>();
+    let shadow = skip_shadow(commits);
+    let yields = commits
+        .iter()
+        .enumerate()
+        .map(|(i, x)| {
+            information_yield(
+                x.status == Status::Unknown && !existing_alloc.contains(&i),
+                shadow[i],
+            )
+        })
+        .collect::<Vec<_>>();
+    information_cuts(&masses, &yields, existing_alloc, count)
 }
 
-pub fn init(commits: &[String], runners: usize, check_bookends: bool) -> State {
-    let runner_commits = initial_alloc(commits, runners, check_bookends);
+/// Start a bisection over `commits` with a script whose results can be wrong, see [`Flaky`].
+/// The bounds and commits that already have a status are trusted, so bookends aren't checked.
+pub fn init_flaky(mut commits: Vec<CommitState>, runners: usize, flaky: Flaky) -> State {
+    normalize_prior(&mut commits);
+    condition_on_known(&mut commits);
+
+    let runner_commits = flaky_alloc(&commits, runners, &[]);
     let runner_start_times = runner_commits.iter().map(|_| 0.0).collect();
 
     State {
         runtime_samples: vec![],
-        commits: commits
-            .iter()
-            .map(|x| CommitState {
-                hash: x.clone(),
-                status: Status::Unknown,
-            })
-            .collect(),
+        commits,
+        runners: Runners {
+            commits: runner_commits,
+            start_times: runner_start_times,
+            total: runners,
+        },
+        check_bookends: false,
+        flaky: Some(flaky),
+    }
+}
+
+/// Start a bisection over `commits`, ordered from old to new.
+/// Commit weights are normalized into prior probabilities. Commits that already have a status aren't retested.
+pub fn init<F>(mut commits: Vec<CommitState>, runners: usize, check_bookends: bool) -> State
+where
+    F: Allocator,
+{
+    normalize_prior(&mut commits);
+
+    let mut runner_commits = if is_linear(&commits) {
+        F::alloc_initial(&commits, runners, check_bookends)
+    } else {
+        F::alloc_dag(runners, &[], &commits, &suspects(&commits), check_bookends)
+    };
+
+    // Don't rerun commits marked by an earlier bisection, or commits they already decide.
+    // Put their runners somewhere useful instead.
+    let suspect = suspect_mask(&commits);
+    let useful = |x: &usize| suspect[*x] && commits[*x].status == Status::Unknown;
+    if !runner_commits.iter().all(useful) {
+        runner_commits.retain(useful);
+        let new_runners = alloc_free::<F>(
+            &commits,
+            runners,
+            &runner_commits,
+            check_bookends,
+            &Timing::default(),
+        );
+        runner_commits.extend(new_runners);
+    }
+    let runner_start_times = runner_commits.iter().map(|_| 0.0).collect();
+
+    State {
+        runtime_samples: vec![],
+        commits,
         runners: Runners {
             commits: runner_commits,
             start_times: runner_start_times,
             total: runners,
         },
         check_bookends,
+        flaky: None,
+    }
+}
+
+/// Mark commits that were already marked in `git bisect`, matched by full hash. The last mark of a commit wins.
+/// When checking bookends, the oldest and newest commit are left alone so the script still validates them.
+pub fn mark_known(commits: &mut [CommitState], known: &[(Status, String)], check_bookends: bool) {
+    let bookends = [0, commits.len().saturating_sub(1)];
+
+    for (idx, commit) in commits.iter_mut().enumerate() {
+        if check_bookends && bookends.contains(&idx) {
+            continue;
+        }
+
+        if let Some((status, _)) = known.iter().rev().find(|(_, hash)| *hash == commit.hash) {
+            commit.status = *status;
+        }
+    }
+}
+
+/// Pick an interrupted bisection back up with `runners` runners.
+/// Commits marked since the state was saved (eg. in `git bisect log`) are applied first.
+/// Runners that were in flight are kept if they are still useful. Free runners are allocated.
+pub fn resume<F>(state: State, known: &[(Status, String)], runners: usize) -> State
+where
+    F: Allocator,
+{
+    let mut commits = state.commits;
+    mark_known(&mut commits, known, state.check_bookends);
+    if state.flaky.is_some() {
+        condition_on_known(&mut commits);
+    }
+
+    let suspect = suspect_mask(&commits);
+    let remaining_runners = state
+        .runners
+        .commits
+        .into_iter()
+        .filter(|x| suspect[*x] && commits[*x].status == Status::Unknown)
+        .take(runners)
+        .collect::<Vec<_>>();
+
+    let new_runners = match state.flaky {
+        Some(_) => flaky_alloc(&commits, runners, &remaining_runners),
+        // Runners that were in flight are restarted along with the new ones
+        None => alloc_free::<F>(
+            &commits,
+            runners,
+            &remaining_runners,
+            state.check_bookends,
+            &Timing {
+                runtime_samples: &state.runtime_samples,
+                ..Default::default()
+            },
+        ),
+    };
+
+    let runner_commits = [remaining_runners, new_runners].concat();
+    let runner_start_times = runner_commits.iter().map(|_| 0.0).collect();
+
+    State {
+        runtime_samples: state.runtime_samples,
+        commits,
+        runners: Runners {
+            commits: runner_commits,
+            start_times: runner_start_times,
+            total: runners,
+        },
+        check_bookends: state.check_bookends,
+        flaky: state.flaky,
     }
 }
 
@@ -95,6 +669,173 @@ fn invalidate_runners(
     }
 }
 
+/// Runners that are still useful after a commit of a history with merges got its `status`, and the ones that aren't.
+/// A good commit clears its ancestors, a bad commit its descendants and every commit that isn't its ancestor.
+fn invalidate_dag_runners(
+    commits: &[CommitState],
+    runners: &[usize],
+    index: usize,
+) -> (Vec<usize>, HashSet<usize>) {
+    let suspect = suspect_mask(commits);
+    let (remaining, invalidated): (Vec<usize>, Vec<usize>) =
+        runners.iter().partition(|x| **x != index && suspect[**x]);
+
+    (remaining, invalidated.into_iter().collect())
+}
+
+/// Whether the commits form a single chain, with every commit the parent of the next.
+/// Commits built without any parents are taken to be a chain, too.
+pub fn is_linear(commits: &[CommitState]) -> bool {
+    commits.iter().all(|x| x.parents.is_empty())
+        || commits
+            .iter()
+            .enumerate()
+            .skip(1)
+            .all(|(i, x)| *x.parents == [i - 1])
+}
+
+/// Mark every ancestor of `commit`, including itself.
+pub fn mark_ancestors(commits: &[CommitState], commit: usize, marked: &mut [bool]) {
+    marked[commit] = true;
+    for x in (0..=commit).rev() {
+        if marked[x] {
+            for parent in commits[x].parents.iter() {
+                marked[*parent] = true;
+            }
+        }
+    }
+}
+
+fn suspect_mask(commits: &[CommitState]) -> Vec<bool> {
+    let mut suspect = vec![false; commits.len()];
+    for x in suspects(commits) {
+        suspect[x] = true;
+    }
+    suspect
+}
+
+/// Commits that may still be the first bad commit, ordered from old to new.
+/// That's every commit that isn't an ancestor of a good commit, but is an ancestor of every bad commit.
+/// For a history without merges, it's the bisection range and the bad commit right after it.
+pub fn suspects(commits: &[CommitState]) -> Vec<usize> {
+    if is_linear(commits) {
+        let (range_start, range) = get_range(commits);
+        return (range_start..(range_start + range.len() + 1).min(commits.len())).collect();
+    }
+
+    let mut cleared = vec![false; commits.len()];
+    for (i, commit) in commits.iter().enumerate().rev() {
+        if commit.status == Status::Good {
+            cleared[i] = true;
+        }
+        if cleared[i] {
+            for parent in commit.parents.iter() {
+                cleared[*parent] = true;
+            }
+        }
+    }
+
+    let mut suspect = cleared.iter().map(|x| !x).collect::<Vec<_>>();
+    for (i, _) in commits
+        .iter()
+        .enumerate()
+        .filter(|(_, x)| x.status == Status::Bad)
+    {
+        let mut ancestors = vec![false; commits.len()];
+        mark_ancestors(commits, i, &mut ancestors);
+        for (suspect, ancestor) in suspect.iter_mut().zip(ancestors) {
+            *suspect &= ancestor;
+        }
+    }
+
+    (0..commits.len()).filter(|x| suspect[*x]).collect()
+}
+
+/// Commits the first bad commit could be any of once only skipped commits are left to test, like `git bisect` lists them.
+/// That's the skipped suspects and the bad commit after them. Empty while there's something left to test, or once the
+/// first bad commit is known.
+pub fn skipped_suspects(commits: &[CommitState]) -> Vec<usize> {
+    let suspects = suspects(commits);
+    let only_skipped_left = suspects
+        .iter()
+        .all(|x| commits[*x].status != Status::Unknown);
+    if suspects.len() > 1 && only_skipped_left {
+        suspects
+    } else {
+        vec![]
+    }
+}
+
+/// When the runners in flight started and how long finished runners took, for allocators that plan around runtimes.
+#[derive(Debug, Default)]
+pub struct Timing<'a> {
+    /// Runtime of every runner that finished so far
+    pub runtime_samples: &'a [f64],
+    /// Start time of every runner in flight, in the order of `existing_alloc`.
+    /// Runners past its end start along with the new ones.
+    pub start_times: &'a [f64],
+    /// Time new runners start at
+    pub now: f64,
+}
+
+impl Timing<'_> {
+    /// Start time of the `i`th runner in flight.
+    pub fn start_time(&self, i: usize) -> f64 {
+        self.start_times.get(i).copied().unwrap_or(self.now)
+    }
+}
+
+/// Expected time a runner that started at `start` finishes, given that it's still running at `now`.
+/// That's the mean of the runtimes so far that are longer than it has been running.
+/// A runner that has been running longer than any of them is expected to finish any moment now.
+/// None before any runner finished.
+pub fn expected_finish(runtime_samples: &[f64], start: f64, now: f64) -> Option<f64> {
+    if runtime_samples.is_empty() {
+        return None;
+    }
+
+    let elapsed = now - start;
+    let longer = runtime_samples
+        .iter()
+        .filter(|x| **x > elapsed)
+        .collect::<Vec<_>>();
+    if longer.is_empty() {
+        return Some(now);
+    }
+
+    Some(start + longer.iter().copied().sum::<f64>() / longer.len() as f64)
+}
+
+/// Allocate free runners over what's left of the bisection, with or without merges.
+fn alloc_free<F>(
+    commits: &[CommitState],
+    runners: usize,
+    existing_alloc: &[usize],
+    check_bookends: bool,
+    timing: &Timing,
+) -> Vec<usize>
+where
+    F: Allocator,
+{
+    if is_linear(commits) {
+        F::alloc_timed(
+            runners,
+            existing_alloc,
+            get_range(commits),
+            check_bookends,
+            timing,
+        )
+    } else {
+        F::alloc_dag(
+            runners,
+            existing_alloc,
+            commits,
+            &suspects(commits),
+            check_bookends,
+        )
+    }
+}
+
 /// Returns starting commit idx and slice
 pub fn get_range(commits: &[CommitState]) -> (usize, &[CommitState]) {
     let oldest_good_idx = commits
@@ -134,6 +875,10 @@ where
     );
     assert!(time.is_sign_positive(), "Time is non-positive: {time}");
 
+    if let Some(flaky) = state.flaky {
+        return step_flaky(state, flaky, status, index, runtime, time);
+    }
+
     let commits = state
         .commits
         .iter()
@@ -141,8 +886,8 @@ where
         .map(|(i, c)| {
             if i == index {
                 CommitState {
-                    hash: c.hash.clone(),
                     status,
+                    ..c.clone()
                 }
             } else {
                 c.clone()
@@ -150,31 +895,54 @@ where
         })
         .collect::<Vec<_>>();
 
-    let (remaining_runners, invalidated_runners) =
-        invalidate_runners(&state.runners.commits, index, status);
-    let bisection_range = get_range(&commits);
-    let new_runners = F::alloc_runners(
+    let linear = is_linear(&commits);
+    let (remaining_runners, invalidated_runners) = if linear {
+        invalidate_runners(&state.runners.commits, index, status)
+    } else {
+        invalidate_dag_runners(&commits, &state.runners.commits, index)
+    };
+    let runtime_samples = [state.runtime_samples.clone(), vec![runtime]].concat();
+    let remaining_start_times = start_times(&state.runners, &remaining_runners, &[], time);
+    let new_runners = alloc_free::<F>(
+        &commits,
         state.runners.total,
         &remaining_runners,
-        bisection_range,
         state.check_bookends,
+        &Timing {
+            runtime_samples: &runtime_samples,
+            start_times: &remaining_start_times,
+            now: time,
+        },
     );
 
-    assert!(
-        new_runners.iter().all(|x| bisection_range.0 <= *x),
-        "Allocator scheduled known-good commit. Runners: {:?}, lower bound: {}",
-        new_runners,
-        bisection_range.0
-    );
-    assert!(
-        new_runners
-            .iter()
-            .all(|x| *x < bisection_range.0 + bisection_range.1.len()),
-        "Allocator scheduled known-bad commit. Runners: {:?}, upper bound: {}",
-        new_runners,
-        bisection_range.0 + bisection_range.1.len() - 1
-    );
+    if linear {
+        let bisection_range = get_range(&commits);
+        assert!(
+            new_runners.iter().all(|x| bisection_range.0 <= *x),
+            "Allocator scheduled known-good commit. Runners: {:?}, lower bound: {}",
+            new_runners,
+            bisection_range.0
+        );
+        assert!(
+            new_runners
+                .iter()
+                .all(|x| *x < bisection_range.0 + bisection_range.1.len()),
+            "Allocator scheduled known-bad commit. Runners: {:?}, upper bound: {}",
+            new_runners,
+            bisection_range.0 + bisection_range.1.len() - 1
+        );
+    } else {
+        let suspect = suspect_mask(&commits);
+        assert!(
+            new_runners
+                .iter()
+                .all(|x| suspect[*x] && commits[*x].status == Status::Unknown),
+            "Allocator scheduled a commit that can't be the first bad commit. Runners: {:?}",
+            new_runners
+        );
+    }
 
+    let runner_start_times = start_times(&state.runners, &remaining_runners, &new_runners, time);
     let mut runners = vec![];
     runners.extend(remaining_runners);
     runners.extend(&new_runners);
@@ -186,8 +954,6 @@ where
         runners.len(),
         state.runners.total
     );
-    let runner_start_times = runners.iter().map(|_| time).collect();
-
     let runners = Runners {
         commits: runners,
         start_times: runner_start_times,
@@ -195,24 +961,103 @@ where
     };
 
     assert!(
-        !runners.commits.is_empty() || get_range(&commits).1.is_empty(),
-        "Scheduler fail! Commits remaining with no runners scheduled. Runners commit indexes: {:?} Commit range: {:?}",
+        !runners.commits.is_empty()
+            || suspects(&commits)
+                .iter()
+                .all(|x| commits[*x].status != Status::Unknown),
+        "Scheduler fail! Commits remaining with no runners scheduled. Runners commit indexes: {:?} Suspects: {:?}",
         runners.commits,
-        get_range(&state.commits).1
+        suspects(&commits)
     );
 
     (
         State {
-            runtime_samples: [state.runtime_samples.clone(), vec![runtime]].concat(),
+            runtime_samples,
             commits: commits.to_vec(),
             runners,
             check_bookends: state.check_bookends,
+            flaky: None,
         },
         invalidated_runners,
         new_runners,
     )
 }
 
+/// Start times of the `remaining` runners, which keep running, followed by the `new` runners starting at `time`.
+fn start_times(runners: &Runners, remaining: &[usize], new: &[usize], time: f64) -> Vec<f64> {
+    remaining
+        .iter()
+        .map(|x| {
+            runners
+                .commits
+                .iter()
+                .position(|commit| commit == x)
+                .map_or(time, |i| runners.start_times[i])
+        })
+        .chain(new.iter().map(|_| time))
+        .collect()
+}
+
+/// Like [`step`], but the result only updates the weights, see [`Flaky`].
+/// Once a commit is the first bad commit with enough confidence, it's marked bad and its parents good, and no more runners are placed.
+fn step_flaky(
+    state: &State,
+    flaky: Flaky,
+    status: Status,
+    index: usize,
+    runtime: f64,
+    time: f64,
+) -> (State, HashSet<usize>, Vec<usize>) {
+    let mut commits = state.commits.clone();
+    if status == Status::Skip {
+        // Untestable, so never tried again
+        commits[index].status = Status::Skip;
+    } else {
+        flaky.observe(&mut commits, index, status);
+    }
+
+    let (mut remaining_runners, mut invalidated_runners): (Vec<usize>, Vec<usize>) =
+        state.runners.commits.iter().partition(|x| **x != index);
+
+    let new_runners = match flaky.decided(&commits) {
+        Some(first_bad) => {
+            // Settle on it, so it's reported like the first bad commit of any other bisection
+            commits[first_bad].status = Status::Bad;
+            let parents = if is_linear(&commits) {
+                first_bad.checked_sub(1).into_iter().collect()
+            } else {
+                commits[first_bad].parents.to_vec()
+            };
+            for parent in parents {
+                commits[parent].status = Status::Good;
+            }
+
+            invalidated_runners.append(&mut remaining_runners);
+            vec![]
+        }
+        None => flaky_alloc(&commits, state.runners.total, &remaining_runners),
+    };
+
+    let runner_start_times = start_times(&state.runners, &remaining_runners, &new_runners, time);
+    let runner_commits = [remaining_runners, new_runners.clone()].concat();
+
+    (
+        State {
+            runtime_samples: [state.runtime_samples.clone(), vec![runtime]].concat(),
+            commits,
+            runners: Runners {
+                commits: runner_commits,
+                start_times: runner_start_times,
+                total: state.runners.total,
+            },
+            check_bookends: state.check_bookends,
+            flaky: state.flaky,
+        },
+        invalidated_runners.into_iter().collect(),
+        new_runners,
+    )
+}
+
 #[tested_trait]
 pub trait Allocator {
     fn alloc_runners(
@@ -222,6 +1067,41 @@ pub trait Allocator {
         check_bookends: bool,
     ) -> Vec<usize>;
 
+    /// Like `alloc_runners`, knowing when the runners in `existing_alloc` started and how long runners took so far.
+    fn alloc_timed(
+        runners: usize,
+        existing_alloc: &[usize],
+        bisection_range: (usize, &[CommitState]),
+        check_bookends: bool,
+        _timing: &Timing,
+    ) -> Vec<usize> {
+        Self::alloc_runners(runners, existing_alloc, bisection_range, check_bookends)
+    }
+
+    /// Allocate runners before any results are known.
+    fn alloc_initial(commits: &[CommitState], runners: usize, check_bookends: bool) -> Vec<usize> {
+        initial_alloc(commits.len(), runners, check_bookends)
+    }
+
+    /// Allocate runners in a history with merges, where what's left of the bisection isn't a range.
+    /// `suspects` are the commits that may still be the first bad commit, see [`suspects`].
+    fn alloc_dag(
+        runners: usize,
+        existing_alloc: &[usize],
+        commits: &[CommitState],
+        suspects: &[usize],
+        check_bookends: bool,
+    ) -> Vec<usize> {
+        dag_cuts(
+            runners,
+            existing_alloc,
+            commits,
+            suspects,
+            check_bookends,
+            prior_mass,
+        )
+    }
+
     #[test]
     fn alloc_respects_range_offset() {
         let runners = 1;
@@ -229,6 +1109,9 @@ pub trait Allocator {
         let commit_range = vec![CommitState {
             hash: "ONLY_COMMIT".to_string(),
             status: Status::Unknown,
+            weight: 1.0,
+            meta: Default::default(),
+            parents: Default::default(),
         }];
         let bisection_range = (12, commit_range.as_slice());
         let allocated_runners =
@@ -251,18 +1134,30 @@ pub trait Allocator {
             CommitState {
                 hash: "GOOD_COMMIT".to_string(),
                 status: Status::Unknown,
+                weight: 0.25,
+                meta: Default::default(),
+                parents: Default::default(),
             },
             CommitState {
                 hash: "BAD_COMMIT".to_string(),
                 status: Status::Unknown,
+                weight: 0.25,
+                meta: Default::default(),
+                parents: Default::default(),
             },
             CommitState {
                 hash: "BAD_COMMIT".to_string(),
                 status: Status::Unknown,
+                weight: 0.25,
+                meta: Default::default(),
+                parents: Default::default(),
             },
             CommitState {
                 hash: "BAD_COMMIT".to_string(),
                 status: Status::Unknown,
+                weight: 0.25,
+                meta: Default::default(),
+                parents: Default::default(),
             },
         ];
         let bisection_range = (0, commit_range.as_slice());
@@ -283,102 +1178,723 @@ impl Allocator for DumbAllocator {
         bisection_range: (usize, &[CommitState]),
         check_bookends: bool,
     ) -> Vec<usize> {
-        let mut bounds_start = bisection_range.0;
-        let bounds_end = bisection_range.0 + bisection_range.1.len();
-
-        if (bisection_range.1.len() as i64) <= runners as i64 {
-            // We can allocate everything!
-            return (bounds_start..bounds_end)
-                .filter(|x| !existing_alloc.contains(x))
-                .collect();
-        }
+        alloc_around_bookends(
+            runners,
+            existing_alloc,
+            bisection_range,
+            check_bookends,
+            |bounds, count, testable| {
+                // We have to make decisions :(
+                // Dumbly just assign to the next elem
+                bounds.filter(|x| testable(*x)).take(count).collect()
+            },
+        )
+    }
+}
 
-        let mut new_runners = HashSet::new();
-        let mut new_runners_to_allocate = runners - existing_alloc.len();
+pub struct BasicAllocator;
+#[test_impl]
+impl Allocator for BasicAllocator {
+    fn alloc_runners(
+        runners: usize,
+        existing_alloc: &[usize],
+        bisection_range: (usize, &[CommitState]),
+        check_bookends: bool,
+    ) -> Vec<usize> {
+        alloc_around_bookends(
+            runners,
+            existing_alloc,
+            bisection_range,
+            check_bookends,
+            |bounds, count, testable| {
+                // We have to make decisions :(
+                // Space new runners out equally over the range.
+                let valid_additions = bounds.clone().filter(|x| testable(*x)).collect::<Vec<_>>();
 
-        // If runners are >= 2, then the bounds would already be scheduled.
-        if check_bookends && runners == 1 && bisection_range.0 == 0 {
-            new_runners.insert(0);
-            bounds_start += 1;
-            new_runners_to_allocate -= 1;
-        }
+                let spacing = valid_additions.len() / (count + 1);
 
-        // We have to make decisions :(
-        // Dumbly just assign to the next elem
-        new_runners.extend(
-            (bounds_start..bounds_end)
-                .filter(|x| !existing_alloc.contains(x))
-                .take(new_runners_to_allocate),
-        );
+                let idxes: Vec<_> = (0..count).map(|x| x * spacing + spacing).collect();
+
+                // Off skipped commits and the ones around them, which are likely to be skipped too
+                let mut new_runners = HashSet::new();
+                let shadow = skip_shadow(bisection_range.1);
+                for x in idxes
+                    .into_iter()
+                    .filter(|x| !existing_alloc.contains(&(bounds.start + x)))
+                {
+                    let clear = nearest_clear(
+                        bounds.clone(),
+                        bounds.start + x,
+                        spacing / 2,
+                        |x| testable(x) && !new_runners.contains(&x),
+                        |x| shadow[x - bisection_range.0],
+                    );
+                    new_runners.extend(clear);
+                }
+
+                // If after deduplicating we have runners, just greedily allocate them linearly
+                let count = count - new_runners.len();
+                if count != 0 {
+                    // Dumbly allocate
+                    new_runners.extend(
+                        valid_additions
+                            .into_iter()
+                            .filter(|x| !new_runners.contains(x))
+                            .take(count)
+                            .collect::<Vec<usize>>(),
+                    );
+                }
+
+                new_runners.into_iter().collect()
+            },
+        )
+    }
 
-        new_runners.into_iter().collect()
+    /// Splits the suspects by count, ignoring their weight.
+    fn alloc_dag(
+        runners: usize,
+        existing_alloc: &[usize],
+        commits: &[CommitState],
+        suspects: &[usize],
+        check_bookends: bool,
+    ) -> Vec<usize> {
+        dag_cuts(
+            runners,
+            existing_alloc,
+            commits,
+            suspects,
+            check_bookends,
+            |_| 1.0,
+        )
     }
 }
 
-pub struct BasicAllocator;
+/// Like the BasicAllocator, but spaces runners out by prior probability mass instead of by index.
+pub struct WeightedAllocator;
 #[test_impl]
-impl Allocator for BasicAllocator {
+impl Allocator for WeightedAllocator {
     fn alloc_runners(
         runners: usize,
         existing_alloc: &[usize],
         bisection_range: (usize, &[CommitState]),
         check_bookends: bool,
     ) -> Vec<usize> {
-        let mut bounds_start = bisection_range.0;
-        let mut bounds_end = bisection_range.0 + bisection_range.1.len();
-
-        if (bisection_range.1.len() as i64) <= runners as i64 {
-            // We can allocate everything!
-            return (bounds_start..bounds_end)
-                .filter(|x| !existing_alloc.contains(x))
-                .collect();
-        }
+        alloc_around_bookends(
+            runners,
+            existing_alloc,
+            bisection_range,
+            check_bookends,
+            |bounds, count, testable| {
+                // Space new runners out at equal probability mass over the range.
+                let valid_additions = bounds
+                    .clone()
+                    .filter(|x| testable(*x))
+                    .map(|x| (x, prior_mass(&bisection_range.1[x - bisection_range.0])))
+                    .collect::<Vec<_>>();
 
-        let mut new_runners = HashSet::new();
-        let mut new_runners_to_allocate = runners - existing_alloc.len();
+                // Off the commits around skipped ones, which are likely to be skipped too
+                let mut new_runners = HashSet::new();
+                let shadow = skip_shadow(bisection_range.1);
+                let shadowed = |x: usize| shadow[x - bisection_range.0];
+                let reach = valid_additions.len() / (count + 1) / 2;
+                for x in mass_quantiles(&valid_additions, count) {
+                    if !shadowed(x) {
+                        new_runners.insert(x);
+                        continue;
+                    }
+                    let clear = nearest_clear(
+                        bounds.clone(),
+                        x,
+                        reach,
+                        |x| testable(x) && !new_runners.contains(&x),
+                        shadowed,
+                    );
+                    new_runners.extend(clear);
+                }
 
-        // If runners are >= 2, then the bounds would already be scheduled.
-        if check_bookends && runners == 1 && bisection_range.0 == 0 {
-            new_runners.insert(0);
-            bounds_start += 1;
-            bounds_end -= 1;
-            new_runners_to_allocate -= 1;
-        }
+                // If several quantiles landed on the same commit, greedily allocate the rest linearly
+                let count = count - new_runners.len();
+                if count != 0 {
+                    new_runners.extend(
+                        valid_additions
+                            .into_iter()
+                            .map(|(x, _)| x)
+                            .filter(|x| !new_runners.contains(x))
+                            .take(count)
+                            .collect::<Vec<usize>>(),
+                    );
+                }
 
-        // We have to make decisions :(
-        // Space new runners out equally over the range.
-        let valid_additions = (bounds_start..bounds_end)
-            .filter(|x| !existing_alloc.contains(x))
-            .collect::<Vec<_>>();
+                new_runners.into_iter().collect()
+            },
+        )
+    }
+
+    fn alloc_initial(commits: &[CommitState], runners: usize, check_bookends: bool) -> Vec<usize> {
+        initial_around_bookends(
+            commits.len(),
+            runners,
+            check_bookends,
+            |bookends, lower_bound, upper_bound, runners_to_allocate| {
+                let candidates = (lower_bound..=upper_bound)
+                    .filter(|x| !bookends.contains(x))
+                    .map(|x| (x, prior_mass(&commits[x])))
+                    .collect::<Vec<_>>();
 
-        let spacing = valid_additions.len() / (new_runners_to_allocate + 1);
+                let mut new_runners = mass_quantiles(&candidates, runners_to_allocate);
+                new_runners.dedup();
 
-        let idxes: Vec<_> = (0..new_runners_to_allocate)
-            .map(|x| x * spacing + spacing)
-            .collect();
+                let runners_to_allocate = runners_to_allocate - new_runners.len();
+                if runners_to_allocate != 0 {
+                    new_runners.extend(
+                        candidates
+                            .into_iter()
+                            .map(|(x, _)| x)
+                            .filter(|x| !new_runners.contains(x))
+                            .take(runners_to_allocate)
+                            .collect::<Vec<usize>>(),
+                    );
+                }
+
+                new_runners
+            },
+        )
+    }
+}
+
+/// Place runners to maximize the expected information gained from their combined results, see [`ProbabilisticAllocator`].
+/// Runners in `cuts` count as if their results were known, the rest of `existing_alloc` is only kept clear of.
+fn information_alloc(
+    runners: usize,
+    existing_alloc: &[usize],
+    bisection_range: (usize, &[CommitState]),
+    check_bookends: bool,
+    cuts: &[usize],
+) -> Vec<usize> {
+    alloc_around_bookends(
+        runners,
+        existing_alloc,
+        bisection_range,
+        check_bookends,
+        |bounds, count, testable| {
+            // The known-bad commit after the range may be the first bad commit too.
+            // Its weight isn't visible here, so give it an average share.
+            let mut masses = bisection_range.1
+                [bounds.start - bisection_range.0..bounds.end - bisection_range.0]
+                .iter()
+                .map(prior_mass)
+                .collect::<Vec<_>>();
+            masses.push(masses.iter().sum::<f64>() / masses.len().max(1) as f64);
+
+            let shadow = skip_shadow(bisection_range.1);
+            let yields = bounds
+                .clone()
+                .map(|x| information_yield(testable(x), shadow[x - bisection_range.0]))
+                .chain(once(0.0))
+                .collect::<Vec<_>>();
+            let cuts = cuts
+                .iter()
+                .filter(|x| bounds.contains(x))
+                .map(|x| x - bounds.start)
+                .collect::<Vec<_>>();
 
-        new_runners.extend(
-            idxes
+            information_cuts(&masses, &yields, &cuts, count)
                 .into_iter()
-                .filter(|x| !existing_alloc.contains(&(bounds_start + x)))
-                .map(|x| bounds_start + x),
-        );
+                .map(|x| x + bounds.start)
+                .collect()
+        },
+    )
+}
+
+/// Treats every commit in the range as a candidate for the first bad commit, with its prior mass as the probability.
+/// Runners are placed to maximize the expected information gained from their combined results.
+/// Skipped commits keep their mass, but are never retested, and the commits around them are expected to yield less.
+pub struct ProbabilisticAllocator;
+#[test_impl]
+impl Allocator for ProbabilisticAllocator {
+    fn alloc_runners(
+        runners: usize,
+        existing_alloc: &[usize],
+        bisection_range: (usize, &[CommitState]),
+        check_bookends: bool,
+    ) -> Vec<usize> {
+        information_alloc(
+            runners,
+            existing_alloc,
+            bisection_range,
+            check_bookends,
+            existing_alloc,
+        )
+    }
 
-        // Update remaining runners to allocate
-        new_runners_to_allocate = runners - existing_alloc.len() - new_runners.len();
+    fn alloc_initial(commits: &[CommitState], runners: usize, check_bookends: bool) -> Vec<usize> {
+        initial_around_bookends(
+            commits.len(),
+            runners,
+            check_bookends,
+            |bookends, _, _, runners_to_allocate| {
+                // The oldest commit is good by definition, so it can't be the first bad commit.
+                // The newest commit is bad by definition, so it is the last candidate but never worth testing.
+                let masses = commits[1..].iter().map(prior_mass).collect::<Vec<_>>();
+                let yields = (1..commits.len())
+                    .map(|x| {
+                        information_yield(x != commits.len() - 1 && !bookends.contains(&x), false)
+                    })
+                    .collect::<Vec<_>>();
 
-        // If after deduplicating we have runners, just greedily allocate them linearly
-        if new_runners_to_allocate != 0 {
-            // Dumbly allocate
-            new_runners.extend(
-                valid_additions
+                information_cuts(&masses, &yields, &[], runners_to_allocate)
                     .into_iter()
-                    .filter(|x| !(existing_alloc.contains(x) || new_runners.contains(x)))
-                    .take(new_runners_to_allocate)
-                    .collect::<Vec<usize>>(),
+                    .map(|x| x + 1)
+                    .collect()
+            },
+        )
+    }
+}
+
+/// Like the ProbabilisticAllocator, but plans around how long runners take, to get to the answer sooner rather than in fewer steps.
+/// A runner in flight that is expected to finish before a new one counts as if its result were known, so new runners are
+/// placed on both sides of it. One expected to finish later is only kept clear of, so new runners don't wait on it.
+/// Before any runner finished, runners are spaced out like the BasicAllocator does.
+pub struct RuntimeAllocator;
+#[test_impl]
+impl Allocator for RuntimeAllocator {
+    fn alloc_runners(
+        runners: usize,
+        existing_alloc: &[usize],
+        bisection_range: (usize, &[CommitState]),
+        check_bookends: bool,
+    ) -> Vec<usize> {
+        ProbabilisticAllocator::alloc_runners(
+            runners,
+            existing_alloc,
+            bisection_range,
+            check_bookends,
+        )
+    }
+
+    fn alloc_timed(
+        runners: usize,
+        existing_alloc: &[usize],
+        bisection_range: (usize, &[CommitState]),
+        check_bookends: bool,
+        timing: &Timing,
+    ) -> Vec<usize> {
+        let Some(new_finish) = expected_finish(timing.runtime_samples, timing.now, timing.now)
+        else {
+            return Self::alloc_runners(runners, existing_alloc, bisection_range, check_bookends);
+        };
+
+        let cuts = existing_alloc
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| {
+                expected_finish(timing.runtime_samples, timing.start_time(*i), timing.now)
+                    .is_some_and(|finish| finish <= new_finish)
+            })
+            .map(|(_, x)| *x)
+            .collect::<Vec<_>>();
+
+        information_alloc(
+            runners,
+            existing_alloc,
+            bisection_range,
+            check_bookends,
+            &cuts,
+        )
+    }
+
+    fn alloc_initial(commits: &[CommitState], runners: usize, check_bookends: bool) -> Vec<usize> {
+        // No runtime to plan around yet
+        BasicAllocator::alloc_initial(commits, runners, check_bookends)
+    }
+}
+
+/// Split the commits in `lower..upper` breadth first, the commit at `upper` being bad.
+/// Every split is either a runner in `existing_alloc` or one of up to `count` new runners on a `testable` commit,
+/// away from `shadowed` ones if there's one close enough, see [`nearest_clear`].
+/// Both sides of every split are split again, so whichever way a split goes, the splits on the side that's left are already placed.
+fn both_branches<T, S>(
+    lower: usize,
+    upper: usize,
+    existing_alloc: &[usize],
+    count: usize,
+    testable: T,
+    shadowed: S,
+) -> Vec<usize>
+where
+    T: Fn(usize) -> bool,
+    S: Fn(usize) -> bool,
+{
+    let mut new_runners = vec![];
+    let mut segments = VecDeque::from([(lower, upper)]);
+
+    while new_runners.len() < count {
+        let Some((lower, upper)) = segments.pop_front() else {
+            break;
+        };
+        if lower >= upper {
+            continue;
+        }
+
+        // Halve the candidates for the first bad commit, `lower..=upper`
+        let candidates = upper - lower + 1;
+        let middle = lower + candidates / 2 - 1;
+        let in_flight = existing_alloc
+            .iter()
+            .copied()
+            .filter(|x| (lower..upper).contains(x))
+            .min_by_key(|x| x.abs_diff(middle));
+        let split = match in_flight {
+            Some(split) => split,
+            None => {
+                let Some(split) =
+                    nearest_clear(lower..upper, middle, candidates / 4, &testable, &shadowed)
+                else {
+                    // Nothing left to test in here
+                    continue;
+                };
+                new_runners.push(split);
+                split
+            }
+        };
+
+        segments.push_back((lower, split));
+        segments.push_back((split + 1, upper));
+    }
+
+    new_runners
+}
+
+/// Speculates on both outcomes of every runner in flight. Free runners bisect both sides of it, breadth first,
+/// so the runners that survive its result are already where the next split of what's left would put them.
+/// Splits by count, like the BasicAllocator. Known commits are never retested.
+pub struct SpeculativeAllocator;
+#[test_impl]
+impl Allocator for SpeculativeAllocator {
+    fn alloc_runners(
+        runners: usize,
+        existing_alloc: &[usize],
+        bisection_range: (usize, &[CommitState]),
+        check_bookends: bool,
+    ) -> Vec<usize> {
+        alloc_around_bookends(
+            runners,
+            existing_alloc,
+            bisection_range,
+            check_bookends,
+            |bounds, count, testable| {
+                let shadow = skip_shadow(bisection_range.1);
+                both_branches(
+                    bounds.start,
+                    bounds.end,
+                    existing_alloc,
+                    count,
+                    testable,
+                    |x| shadow[x - bisection_range.0],
+                )
+            },
+        )
+    }
+
+    fn alloc_initial(commits: &[CommitState], runners: usize, check_bookends: bool) -> Vec<usize> {
+        initial_around_bookends(
+            commits.len(),
+            runners,
+            check_bookends,
+            |bookends, _, _, runners_to_allocate| {
+                // Between the oldest commit, good by definition, and the newest, bad by definition
+                both_branches(
+                    1,
+                    commits.len() - 1,
+                    &[],
+                    runners_to_allocate,
+                    |x| commits[x].status == Status::Unknown && !bookends.contains(&x),
+                    |_| false,
+                )
+            },
+        )
+    }
+}
+
+/// Rounds of `runners` runners it takes to find the first bad commit among `candidates`, with every round splitting them `runners + 1` ways.
+fn rounds(candidates: usize, runners: usize) -> usize {
+    let mut rounds = 0;
+    let mut reach = 1;
+    while reach < candidates {
+        reach *= runners + 1;
+        rounds += 1;
+    }
+    rounds
+}
+
+/// Rounds it takes to find every one of `candidates` as the first bad commit, added up.
+/// The rounds differ by one at most, and the ones a round short use up `runners` candidates each.
+fn total_rounds(candidates: usize, runners: usize) -> usize {
+    let rounds = rounds(candidates, runners);
+    if rounds == 0 {
+        return 0;
+    }
+    candidates * rounds - ((runners + 1).pow(rounds as u32) - candidates) / runners
+}
+
+/// Sizes of the parts `cuts` runners split `candidates` into, so that finding the first bad commit takes the fewest rounds of
+/// `runners` runners after. The candidates that take a round more than the rest are kept to as few slots a round down
+/// as possible, and the parts are as even as that allows.
+fn k_ary_parts(candidates: usize, cuts: usize, runners: usize) -> Vec<usize> {
+    let parts = (cuts + 1).min(candidates);
+    if parts == candidates {
+        return vec![1; parts];
+    }
+
+    let mut slots = 1;
+    while parts * slots * (runners + 1) < candidates {
+        slots *= runners + 1;
+    }
+
+    // Every slot split up another round makes room for `runners` more candidates. Which parts they're in makes no
+    // difference to the rounds, so they're spread out for the odd candidate that can't actually be it.
+    let splits = (candidates - parts * slots).div_ceil(runners);
+    let excess = parts * slots + splits * runners - candidates;
+    let mut sizes = (0..parts)
+        .map(|i| slots + runners * (splits / parts + usize::from(i < splits % parts)))
+        .collect::<Vec<_>>();
+    let partial = sizes.iter().rposition(|x| *x > slots).unwrap();
+    sizes[partial] -= excess;
+    sizes
+}
+
+/// The parts of [`k_ary_parts`] in the order they're placed in. Which parts are larger makes no difference to the
+/// rounds. On the `first` allocation they go next to the newest commit, and later on next to the bounds on either side,
+/// which placed runners better in the benches.
+fn k_ary_layout(mut sizes: Vec<usize>, first: bool) -> Vec<usize> {
+    if first {
+        sizes.reverse();
+        return sizes;
+    }
+
+    sizes.sort_unstable_by(|x, y| y.cmp(x));
+    let mut layout = sizes.iter().copied().step_by(2).collect::<Vec<_>>();
+    layout.extend(sizes.iter().copied().skip(1).step_by(2).rev());
+    layout
+}
+
+/// Time it takes to find every one of `candidates` as the first bad commit, added up, in rounds from now if `cuts` new
+/// runners split them. Whether it's among them at all is known after `wait` rounds, but the rounds go on meanwhile.
+fn k_ary_time(candidates: usize, cuts: usize, runners: usize, wait: f64) -> f64 {
+    k_ary_parts(candidates, cuts, runners)
+        .into_iter()
+        .map(|x| {
+            let rounds = rounds(x, runners);
+            let short = match rounds {
+                0 => 0,
+                _ => ((runners + 1).pow(rounds as u32) - x) / runners,
+            };
+            short as f64 * wait.max(rounds as f64)
+                + (x - short) as f64 * wait.max(rounds as f64 + 1.0)
+        })
+        .sum()
+}
+
+/// Place `count` new runners in the candidates `lower..=upper` for the first bad commit, `upper` being known bad.
+/// The range is already split at the `in_flight` runners, and whether the first bad commit is in a part is known once
+/// the runners on either side of it are done. They're given with how many rounds of new runners they take, 1 if they
+/// finish along with them. Every part gets the share of the new runners that takes the most time off on average, see
+/// [`k_ary_time`], and is split as laid out by [`k_ary_layout`]. New runners go on `testable` commits, away from
+/// `shadowed` ones if there's one close enough, see [`nearest_clear`].
+#[allow(clippy::too_many_arguments)]
+fn k_ary_cuts<T, S>(
+    lower: usize,
+    upper: usize,
+    in_flight: &[(usize, f64)],
+    count: usize,
+    runners: usize,
+    first: bool,
+    testable: T,
+    shadowed: S,
+) -> Vec<usize>
+where
+    T: Fn(usize) -> bool,
+    S: Fn(usize) -> bool,
+{
+    let mut in_flight = in_flight
+        .iter()
+        .copied()
+        .filter(|(x, _)| (lower..upper).contains(x))
+        .collect::<Vec<_>>();
+    in_flight.sort_by_key(|(x, _)| *x);
+    let bounds = in_flight.iter().map(|(x, _)| *x).collect::<Vec<_>>();
+    let parts = once(lower)
+        .chain(bounds.iter().map(|x| x + 1))
+        .zip(bounds.iter().copied().chain(once(upper)))
+        .map(|(start, end)| {
+            let testable = (start..end).filter(|x| testable(*x)).count();
+            (start, end - start + 1, testable)
+        })
+        .collect::<Vec<_>>();
+    // The outer bounds are known already
+    let waits = once(0.0)
+        .chain(in_flight.iter().map(|(_, ready)| *ready))
+        .zip(in_flight.iter().map(|(_, ready)| *ready).chain(once(0.0)))
+        .map(|(left, right)| left.max(right))
+        .collect::<Vec<_>>();
+    // Without new runners in a part, the runner done first might as well bisect it on its own until the rest are free
+    let cost = |i: usize, candidates: usize, share: usize| {
+        let time = k_ary_time(candidates, share, runners, waits[i]);
+        if share != 0 {
+            return time;
+        }
+        time.min(candidates as f64 * waits[i] + total_rounds(candidates, 1) as f64)
+    };
+
+    let mut shares = vec![0; parts.len()];
+    for _ in 0..count {
+        let best = parts
+            .iter()
+            .enumerate()
+            .filter(|(i, (_, _, testable))| shares[*i] < *testable)
+            .map(|(i, (_, candidates, _))| {
+                let gain = cost(i, *candidates, shares[i]) - cost(i, *candidates, shares[i] + 1);
+                (i, gain, *candidates)
+            })
+            // Smaller parts first when it's a tie
+            .max_by(|x, y| x.1.total_cmp(&y.1).then(y.2.cmp(&x.2)));
+        match best {
+            Some((i, _, _)) => shares[i] += 1,
+            None => break,
+        }
+    }
+
+    let mut new_runners = vec![];
+    for ((start, candidates, _), share) in parts.into_iter().zip(shares) {
+        if share == 0 {
+            continue;
+        }
+
+        let mut end = start;
+        for size in k_ary_layout(k_ary_parts(candidates, share, runners), first)
+            .into_iter()
+            .take(share)
+        {
+            end += size;
+            let clear = nearest_clear(
+                start..start + candidates - 1,
+                end - 1,
+                size / 2,
+                |x| testable(x) && !new_runners.contains(&x),
+                &shadowed,
             );
+            new_runners.extend(clear);
         }
+    }
 
-        new_runners.into_iter().collect()
+    new_runners
+}
+
+/// Place runners for a k-ary search, see [`KAryAllocator`]. The `i`th runner in flight takes `ready(i)` rounds of new
+/// runners, see [`k_ary_cuts`].
+fn k_ary_alloc<R: Fn(usize) -> f64>(
+    runners: usize,
+    existing_alloc: &[usize],
+    bisection_range: (usize, &[CommitState]),
+    check_bookends: bool,
+    ready: R,
+) -> Vec<usize> {
+    alloc_around_bookends(
+        runners,
+        existing_alloc,
+        bisection_range,
+        check_bookends,
+        |bounds, count, testable| {
+            let shadow = skip_shadow(bisection_range.1);
+            let in_flight = existing_alloc
+                .iter()
+                .enumerate()
+                .map(|(i, x)| (*x, ready(i)))
+                .collect::<Vec<_>>();
+            k_ary_cuts(
+                bounds.start,
+                bounds.end,
+                &in_flight,
+                count,
+                runners,
+                false,
+                testable,
+                |x| shadow[x - bisection_range.0],
+            )
+        },
+    )
+}
+
+/// Places runners for a k-ary search, for the fewest rounds of all runners on average rather than at equal intervals.
+/// Runners in flight split the range already, and the free ones go where they take the most rounds off, so the parts a
+/// round leaves are small enough for the runners to finish them off together. With runtimes to go by, free runners
+/// avoid parts held up by a runner in flight that's expected to take longer, where their rounds would only fill the
+/// wait, and leave a part next to one that's about done to that runner.
+/// Splits by count, like the BasicAllocator. Known commits are never retested.
+pub struct KAryAllocator;
+#[test_impl]
+impl Allocator for KAryAllocator {
+    fn alloc_runners(
+        runners: usize,
+        existing_alloc: &[usize],
+        bisection_range: (usize, &[CommitState]),
+        check_bookends: bool,
+    ) -> Vec<usize> {
+        k_ary_alloc(
+            runners,
+            existing_alloc,
+            bisection_range,
+            check_bookends,
+            |_| 1.0,
+        )
+    }
+
+    fn alloc_timed(
+        runners: usize,
+        existing_alloc: &[usize],
+        bisection_range: (usize, &[CommitState]),
+        check_bookends: bool,
+        timing: &Timing,
+    ) -> Vec<usize> {
+        let Some(new_finish) = expected_finish(timing.runtime_samples, timing.now, timing.now)
+            .filter(|x| *x > timing.now)
+        else {
+            return Self::alloc_runners(runners, existing_alloc, bisection_range, check_bookends);
+        };
+
+        // In rounds of new runners
+        let ready = |i: usize| {
+            let finish = expected_finish(timing.runtime_samples, timing.start_time(i), timing.now)
+                .unwrap_or(new_finish);
+            ((finish - timing.now) / (new_finish - timing.now)).max(0.0)
+        };
+        k_ary_alloc(
+            runners,
+            existing_alloc,
+            bisection_range,
+            check_bookends,
+            ready,
+        )
+    }
+
+    fn alloc_initial(commits: &[CommitState], runners: usize, check_bookends: bool) -> Vec<usize> {
+        initial_around_bookends(
+            commits.len(),
+            runners,
+            check_bookends,
+            |bookends, _, _, runners_to_allocate| {
+                // Between the oldest commit, good by definition, and the newest, bad by definition
+                k_ary_cuts(
+                    1,
+                    commits.len() - 1,
+                    &[],
+                    runners_to_allocate,
+                    runners,
+                    true,
+                    |x| commits[x].status == Status::Unknown && !bookends.contains(&x),
+                    |_| false,
+                )
+            },
+        )
     }
 }
@@ -0,0 +1,270 @@
+/// Access to the repository being bisected, through the `git` binary or in-process.
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    shell::{
+        bisect_report, get_bisect_log, get_commit_meta, get_commits, get_range_meta,
+        get_range_parents, ShellError,
+    },
+    CommitMeta, Status,
+};
+
+/// Everything the bisection needs to know about, or tell, the repository.
+pub trait GitBackend {
+    /// Commits of the bisection in progress, ordered from old to new.
+    /// The first commit is the good commit the range starts after.
+    fn commits(&self) -> Result<Vec<String>, ShellError>;
+
+    /// Author, subject and the files a commit changed.
+    fn commit_meta(&self, hash: &str) -> Result<CommitMeta, ShellError>;
+
+    /// Parents of every commit in the bisection range, in one go.
+    /// The good commit the range starts after may be missing.
+    fn range_parents(&self) -> Result<HashMap<String, Vec<String>>, ShellError>;
+
+    /// Author, subject and changed files of every commit in the bisection range, in one go.
+    /// The good commit the range starts after may be missing.
+    fn range_meta(&self) -> Result<HashMap<String, CommitMeta>, ShellError>;
+
+    /// Commits marked so far, in the order they were marked.
+    fn bisect_log(&self) -> Result<Vec<(Status, String)>, ShellError>;
+
+    /// Mark a commit as good, bad or skipped.
+    fn bisect_report(&self, status: &Status, hash: &str) -> Result<(), ShellError>;
+}
+
+/// Runs `git` for everything. Works wherever git does, but spawns a process per query.
+pub struct CliBackend {
+    repo_path: PathBuf,
+}
+
+impl CliBackend {
+    pub fn new(repo_path: &Path) -> Self {
+        CliBackend {
+            repo_path: repo_path.to_path_buf(),
+        }
+    }
+}
+
+impl GitBackend for CliBackend {
+    fn commits(&self) -> Result<Vec<String>, ShellError> {
+        get_commits(&self.repo_path)
+    }
+
+    fn commit_meta(&self, hash: &str) -> Result<CommitMeta, ShellError> {
+        get_commit_meta(&self.repo_path, hash)
+    }
+
+    fn range_parents(&self) -> Result<HashMap<String, Vec<String>>, ShellError> {
+        get_range_parents(&self.repo_path)
+    }
+
+    fn range_meta(&self) -> Result<HashMap<String, CommitMeta>, ShellError> {
+        get_range_meta(&self.repo_path)
+    }
+
+    fn bisect_log(&self) -> Result<Vec<(Status, String)>, ShellError> {
+        get_bisect_log(&self.repo_path)
+    }
+
+    fn bisect_report(&self, status: &Status, hash: &str) -> Result<(), ShellError> {
+        bisect_report(&self.repo_path, status, &hash.to_string())
+    }
+}
+
+#[cfg(feature = "libgit2")]
+pub use self::libgit2::Libgit2Backend;
+
+#[cfg(feature = "libgit2")]
+mod libgit2 {
+    use std::{collections::HashMap, fs, io::Write, path::Path};
+
+    use git2::{ErrorCode, Oid, Repository, Sort};
+
+    use super::GitBackend;
+    use crate::{
+        shell::{parse_bisect_log, ShellError},
+        CommitMeta, Status,
+    };
+
+    /// Reads the repository with libgit2, without spawning any processes.
+    /// Marks commits by writing the bisect refs and log like `git bisect` does,
+    /// but doesn't check out the next commit to test.
+    pub struct Libgit2Backend {
+        repo: Repository,
+    }
+
+    impl Libgit2Backend {
+        pub fn open(repo_path: &Path) -> Result<Self, ShellError> {
+            match Repository::discover(repo_path) {
+                Ok(repo) => Ok(Libgit2Backend { repo }),
+                Err(err) if err.code() == ErrorCode::NotFound => {
+                    Err(ShellError::NotARepo(repo_path.to_path_buf()))
+                }
+                Err(err) => Err(err.into()),
+            }
+        }
+
+        fn bisecting(&self) -> Result<(), ShellError> {
+            if self.repo.path().join("BISECT_START").exists() {
+                Ok(())
+            } else {
+                Err(ShellError::NoBisect)
+            }
+        }
+
+        fn oid(&self, hash: &str) -> Result<Oid, ShellError> {
+            Ok(self.repo.revparse_single(hash)?.peel_to_commit()?.id())
+        }
+
+        /// Parents of a commit. Merges have more than one, the root commit has none.
+        pub fn parents(&self, hash: &str) -> Result<Vec<String>, ShellError> {
+            let commit = self.repo.find_commit(self.oid(hash)?)?;
+            Ok(commit.parent_ids().map(|x| x.to_string()).collect())
+        }
+
+        /// Appends to BISECT_LOG, which `git bisect log` and `replay` read.
+        fn append_log(&self, entry: &str) -> Result<(), ShellError> {
+            let path = self.repo.path().join("BISECT_LOG");
+            fs::OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(&path)
+                .and_then(|mut log| writeln!(log, "{entry}"))
+                .map_err(|err| ShellError::Io {
+                    context: format!("Failed to write `{}`", path.display()),
+                    err,
+                })
+        }
+    }
+
+    impl GitBackend for Libgit2Backend {
+        fn commits(&self) -> Result<Vec<String>, ShellError> {
+            self.bisecting()?;
+
+            // Same range as `git bisect visualize`: reachable from bad, but not from any good commit
+            let mut revwalk = self.repo.revwalk()?;
+            revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+            let Ok(bad) = self.repo.refname_to_id("refs/bisect/bad") else {
+                return Err(ShellError::EmptyRange);
+            };
+            revwalk.push(bad)?;
+            for good in self.repo.references_glob("refs/bisect/good-*")? {
+                if let Some(oid) = good?.target() {
+                    revwalk.hide(oid)?;
+                }
+            }
+
+            let range = revwalk
+                .map(|oid| Ok(oid?.to_string()))
+                .collect::<Result<Vec<_>, ShellError>>()?;
+            let Some(first_hash) = range.first() else {
+                return Err(ShellError::EmptyRange);
+            };
+
+            // The commit one above the range, known to be good
+            let good_hash = self
+                .repo
+                .find_commit(Oid::from_str(first_hash)?)?
+                .parent_id(0)?
+                .to_string();
+
+            Ok([vec![good_hash], range].concat())
+        }
+
+        fn commit_meta(&self, hash: &str) -> Result<CommitMeta, ShellError> {
+            let commit = self.repo.find_commit(self.oid(hash)?)?;
+            let author = commit.author();
+
+            // Like `git log --name-only`, merges don't list any files
+            let files = if commit.parent_count() > 1 {
+                Vec::new()
+            } else {
+                let parent_tree = match commit.parent(0) {
+                    Ok(parent) => Some(parent.tree()?),
+                    Err(_) => None,
+                };
+                let diff = self.repo.diff_tree_to_tree(
+                    parent_tree.as_ref(),
+                    Some(&commit.tree()?),
+                    None,
+                )?;
+                diff.deltas()
+                    .filter_map(|delta| delta.new_file().path().or(delta.old_file().path()))
+                    .map(|path| path.to_string_lossy().to_string())
+                    .collect()
+            };
+
+            Ok(CommitMeta {
+                files,
+                author: Some(format!(
+                    "{} <{}>",
+                    String::from_utf8_lossy(author.name_bytes()),
+                    String::from_utf8_lossy(author.email_bytes())
+                )),
+                subject: commit.summary().map(|x| x.to_string()),
+            })
+        }
+
+        fn range_parents(&self) -> Result<HashMap<String, Vec<String>>, ShellError> {
+            self.commits()?
+                .into_iter()
+                .map(|hash| Ok((hash.clone(), self.parents(&hash)?)))
+                .collect()
+        }
+
+        fn range_meta(&self) -> Result<HashMap<String, CommitMeta>, ShellError> {
+            // No processes to spawn, so commit by commit is as good as it gets
+            self.commits()?
+                .into_iter()
+                .map(|hash| Ok((hash.clone(), self.commit_meta(&hash)?)))
+                .collect()
+        }
+
+        fn bisect_log(&self) -> Result<Vec<(Status, String)>, ShellError> {
+            self.bisecting()?;
+
+            let path = self.repo.path().join("BISECT_LOG");
+            let log = fs::read_to_string(&path).map_err(|err| ShellError::Io {
+                context: format!("Failed to read `{}`", path.display()),
+                err,
+            })?;
+            Ok(parse_bisect_log(&log))
+        }
+
+        fn bisect_report(&self, status: &Status, hash: &str) -> Result<(), ShellError> {
+            self.bisecting()?;
+
+            let commit = self.repo.find_commit(self.oid(hash)?)?;
+            let hash = commit.id().to_string();
+            let (term, refname) = match status {
+                Status::Good => ("good", format!("refs/bisect/good-{hash}")),
+                Status::Bad => ("bad", "refs/bisect/bad".to_string()),
+                Status::Skip => ("skip", format!("refs/bisect/skip-{hash}")),
+                Status::Unknown => return Err(ShellError::UnknownStatus(hash)),
+            };
+            self.repo
+                .reference(&refname, commit.id(), true, &format!("bisect: {term}"))?;
+
+            // The same entries `git bisect` writes, so `git bisect log` and `replay` keep working
+            self.append_log(&format!(
+                "# {term}: [{hash}] {}\ngit bisect {term} {hash}",
+                commit.summary().unwrap_or_default()
+            ))?;
+
+            // Only the bad commit is left in the range, tell the user like `git bisect` does
+            let commits = self.commits()?;
+            if commits.len() == 2 {
+                let first_bad = self.repo.find_commit(Oid::from_str(&commits[1])?)?;
+                let hash = first_bad.id();
+                let summary = first_bad.summary().unwrap_or_default();
+                println!("{hash} is the first bad commit\n    {summary}");
+                self.append_log(&format!("# first bad commit: [{hash}] {summary}"))?;
+            }
+            Ok(())
+        }
+    }
+}
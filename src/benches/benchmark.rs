@@ -3,7 +3,7 @@
 // TODO: Bench a real git repo
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 
-use git_biasect::alloc::{BasicAllocator, DumbAllocator};
+use git_biasect::alloc::{BasicAllocator, DumbAllocator, ProbabilisticAllocator};
 use git_biasect::tests::alloc_bencher::run_bench;
 use std::time::Duration;
 
@@ -36,6 +36,18 @@ fn criterion_benchmark(c: &mut Criterion) {
             )
         })
     });
+    one_thousand_commits_eight_runners.bench_function("probabilistic", |b| {
+        b.iter(|| {
+            run_bench::<ProbabilisticAllocator>(
+                black_box(1000),
+                black_box(8),
+                black_box(100.0),
+                black_box(1.0),
+                black_box(iters),
+                black_box(true),
+            )
+        })
+    });
     one_thousand_commits_eight_runners.finish();
     let mut one_hundred_commits_eight_runners =
         c.benchmark_group("100 Commits 8 Runners Allocator");
@@ -64,6 +76,18 @@ fn criterion_benchmark(c: &mut Criterion) {
         })
     });
 
+    one_hundred_commits_eight_runners.bench_function("probabilistic", |b| {
+        b.iter(|| {
+            run_bench::<ProbabilisticAllocator>(
+                black_box(100),
+                black_box(8),
+                black_box(100.0),
+                black_box(1.0),
+                black_box(iters),
+                black_box(true),
+            )
+        })
+    });
     one_hundred_commits_eight_runners.finish();
     let mut one_hundred_commits_one_runner = c.benchmark_group("100 Commits 1 Runner Allocator");
     one_hundred_commits_one_runner.bench_function("dumb", |b| {
@@ -90,6 +114,18 @@ fn criterion_benchmark(c: &mut Criterion) {
             )
         })
     });
+    one_hundred_commits_one_runner.bench_function("probabilistic", |b| {
+        b.iter(|| {
+            run_bench::<ProbabilisticAllocator>(
+                black_box(100),
+                black_box(1),
+                black_box(100.0),
+                black_box(1.0),
+                black_box(iters),
+                black_box(true),
+            )
+        })
+    });
     one_hundred_commits_one_runner.finish();
 }
 
@@ -0,0 +1,217 @@
+/// Turn what we know about each commit into prior probabilities of that commit being the first bad commit.
+use std::{fmt, fs, io, path::Path, str::FromStr};
+
+use glob::Pattern;
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::{CommitMeta, CommitState};
+
+/// Bias rules checked into the root of the repository.
+pub const CONFIG_FILE: &str = ".biasect.toml";
+
+/// Multiplier for ignored files. Near-impossible rather than impossible, so a culprit that
+/// only touches ignored files can still be found.
+pub const IGNORE_MULTIPLIER: f64 = 1e-6;
+
+/// Scale the likelihood of commits touching files that match `pattern`.
+/// Parsed from `<glob>=<multiplier>`, eg. `*.c=2` or `docs/**=0`.
+#[derive(Debug, Clone)]
+pub struct BiasRule {
+    pub pattern: Pattern,
+    pub multiplier: f64,
+}
+
+impl FromStr for BiasRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (pattern, multiplier) = s
+            .rsplit_once('=')
+            .ok_or_else(|| format!("Bias rule `{s}` must be of the form `<glob>=<multiplier>`"))?;
+
+        let pattern = Pattern::new(pattern)
+            .map_err(|err| format!("Invalid glob `{pattern}` in bias rule `{s}`: {err}"))?;
+        let multiplier = multiplier.parse::<f64>().map_err(|err| {
+            format!("Invalid multiplier `{multiplier}` in bias rule `{s}`: {err}")
+        })?;
+
+        Ok(BiasRule {
+            multiplier: check_multiplier(multiplier, s)?,
+            pattern,
+        })
+    }
+}
+
+impl fmt::Display for BiasRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.pattern, self.multiplier)
+    }
+}
+
+/// Multiplier for a single file. The first matching rule wins, unmatched files are unbiased.
+fn file_multiplier(file: &str, rules: &[BiasRule]) -> f64 {
+    rules
+        .iter()
+        .find(|rule| rule.pattern.matches(file))
+        .map(|rule| rule.multiplier)
+        .unwrap_or(1.0)
+}
+
+/// Unnormalized weight of a commit's paths: the largest multiplier of any file it touches.
+/// Commits that only touch zero-weighted files get zero. Commits without files are unbiased.
+pub fn path_weight(files: &[String], rules: &[BiasRule]) -> f64 {
+    if files.is_empty() {
+        return 1.0;
+    }
+
+    files
+        .iter()
+        .map(|file| file_multiplier(file, rules))
+        .fold(0.0, f64::max)
+}
+
+/// Scale the likelihood of commits whose author or subject matches `regex`.
+#[derive(Debug, Clone)]
+pub struct RegexRule {
+    pub regex: Regex,
+    pub multiplier: f64,
+}
+
+/// Multiplier of the first rule matching `text`. Unmatched or missing text is unbiased.
+fn regex_multiplier(text: Option<&str>, rules: &[RegexRule]) -> f64 {
+    text.and_then(|text| rules.iter().find(|rule| rule.regex.is_match(text)))
+        .map(|rule| rule.multiplier)
+        .unwrap_or(1.0)
+}
+
+/// Every bias rule that applies to a bisection.
+#[derive(Debug, Clone, Default)]
+pub struct BiasConfig {
+    /// Rules for touched files. The first matching rule wins.
+    pub paths: Vec<BiasRule>,
+    /// Rules for `Name <email>` of the commit author.
+    pub authors: Vec<RegexRule>,
+    /// Rules for the commit subject.
+    pub messages: Vec<RegexRule>,
+}
+
+/// `.biasect.toml` as written by the user.
+/// ```toml
+/// ignore = ["docs/**", "*.md"]
+///
+/// [[path]]
+/// pattern = "*.c"
+/// multiplier = 2
+///
+/// [[author]]
+/// pattern = "\\[bot\\]"
+/// multiplier = 0.1
+///
+/// [[message]]
+/// pattern = "^(docs|style):"
+/// multiplier = 0.1
+/// ```
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct RawConfig {
+    #[serde(default)]
+    ignore: Vec<String>,
+    #[serde(default)]
+    path: Vec<RawRule>,
+    #[serde(default)]
+    author: Vec<RawRule>,
+    #[serde(default)]
+    message: Vec<RawRule>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawRule {
+    pattern: String,
+    multiplier: f64,
+}
+
+fn check_multiplier(multiplier: f64, pattern: &str) -> Result<f64, String> {
+    if !multiplier.is_finite() || multiplier.is_sign_negative() {
+        return Err(format!(
+            "Multiplier of rule `{pattern}` must be a non-negative number"
+        ));
+    }
+
+    Ok(multiplier)
+}
+
+fn regex_rules(rules: Vec<RawRule>) -> Result<Vec<RegexRule>, String> {
+    rules
+        .into_iter()
+        .map(|rule| {
+            Ok(RegexRule {
+                regex: Regex::new(&rule.pattern)
+                    .map_err(|err| format!("Invalid regex `{}`: {err}", rule.pattern))?,
+                multiplier: check_multiplier(rule.multiplier, &rule.pattern)?,
+            })
+        })
+        .collect()
+}
+
+impl BiasConfig {
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty() && self.authors.is_empty() && self.messages.is_empty()
+    }
+
+    pub fn parse(config: &str) -> Result<Self, String> {
+        let raw: RawConfig = toml::from_str(config).map_err(|err| err.to_string())?;
+
+        let glob = |pattern: &str| {
+            Pattern::new(pattern).map_err(|err| format!("Invalid glob `{pattern}`: {err}"))
+        };
+
+        // Ignore rules come first so they can't be overridden by a broader path rule.
+        let ignored = raw.ignore.iter().map(|pattern| {
+            Ok(BiasRule {
+                pattern: glob(pattern)?,
+                multiplier: IGNORE_MULTIPLIER,
+            })
+        });
+        let paths = raw.path.iter().map(|rule| {
+            Ok(BiasRule {
+                pattern: glob(&rule.pattern)?,
+                multiplier: check_multiplier(rule.multiplier, &rule.pattern)?,
+            })
+        });
+
+        Ok(BiasConfig {
+            paths: ignored.chain(paths).collect::<Result<_, String>>()?,
+            authors: regex_rules(raw.author)?,
+            messages: regex_rules(raw.message)?,
+        })
+    }
+
+    /// Load a config file. A missing file is an empty config.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(config) => BiasConfig::parse(&config)
+                .map_err(|err| format!("Invalid bias config `{}`: {err}", path.display())),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(BiasConfig::default()),
+            Err(err) => Err(format!(
+                "Failed to read bias config `{}`: {err}",
+                path.display()
+            )),
+        }
+    }
+
+    /// Unnormalized weight of a commit. The path, author and subject multipliers stack.
+    pub fn commit_weight(&self, meta: &CommitMeta) -> f64 {
+        path_weight(&meta.files, &self.paths)
+            * regex_multiplier(meta.author.as_deref(), &self.authors)
+            * regex_multiplier(meta.subject.as_deref(), &self.messages)
+    }
+}
+
+/// Weight every commit by its metadata. `alloc::init` normalizes the weights into priors.
+pub fn weigh_commits(commits: &mut [CommitState], config: &BiasConfig) {
+    for commit in commits.iter_mut() {
+        commit.weight = config.commit_weight(&commit.meta);
+    }
+}
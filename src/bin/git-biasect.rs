@@ -1,22 +1,40 @@
 use argh::FromArgs;
-use git_biasect::alloc::{init, step, BasicAllocator};
+use git_biasect::alloc::{
+    init, init_flaky, mark_known, resume, skipped_suspects, step, suspects, Allocator,
+    BasicAllocator, KAryAllocator, ProbabilisticAllocator, RuntimeAllocator, SpeculativeAllocator,
+    WeightedAllocator,
+};
+#[cfg(not(feature = "libgit2"))]
+use git_biasect::backend::CliBackend;
+use git_biasect::backend::GitBackend;
+#[cfg(feature = "libgit2")]
+use git_biasect::backend::Libgit2Backend;
+use git_biasect::bias::{weigh_commits, BiasConfig, BiasRule, CONFIG_FILE};
+use git_biasect::exit::{CodeSet, Exit, ExitCodeMap, Outcome};
+use git_biasect::flaky::{most_likely, Flaky};
+use git_biasect::persist::{
+    load_state, log_path, logs_dir, remove_state, reset_logs, save_state, state_path, tail_log,
+};
+use git_biasect::reaper::{Event, Reaper};
 use git_biasect::shell::{
-    bisect_report, get_commit_files, get_commits, reproducer_shell_commands, run_script,
-    worktree_prune,
+    get_git_dir, get_repo_root, kill_process_group, process_group_exists,
+    reproducer_shell_commands, run_script, worktree_prune, ShellError,
 };
+use git_biasect::timeout::Timeout;
 use git_biasect::visualize::print_commits;
-use git_biasect::{CommitState, Status};
-use rand::seq::IteratorRandom;
-use rand::{rngs::StdRng, SeedableRng};
-use std::collections::HashSet;
+use git_biasect::worktree::{Clean, Worktrees};
+use git_biasect::{short_hash, CommitMeta, CommitState, State, Status};
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::os::unix::process::ExitStatusExt;
+use std::mem;
 use std::path::{Path, PathBuf};
-use std::process::Child;
-use std::str;
-use std::thread::sleep;
+use std::process::{self, ExitCode};
+use std::str::{self, FromStr};
 use std::time::{Duration, Instant};
 
+/// How long cancelled runners get to exit on SIGTERM before their process group is killed.
+const KILL_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
 /**
 Git Biasect
 */
@@ -49,6 +67,54 @@ struct RunOptions {
     #[argh(option, short = 'C', default = "PathBuf::from(\".\")")]
     repo_path: PathBuf,
 
+    /// bias rule of the form "<glob>=<multiplier>". Eg. "*.c=2" or "docs/**=0". Can be repeated
+    #[argh(option, short = 'b')]
+    bias: Vec<BiasRule>,
+
+    /// allocator used to place runners: basic, weighted, probabilistic, runtime, speculative or kary. Defaults to weighted if any bias rules are given or configured
+    #[argh(option, short = 'a')]
+    allocator: Option<AllocatorKind>,
+
+    /// exit codes of the script that mark a commit as skipped. Eg. "124,125". Defaults to 125, like git bisect run
+    #[argh(option)]
+    skip_codes: Option<CodeSet>,
+
+    /// exit codes of the script that abort the bisection. Eg. "128-255". Defaults to 128 and above, like git bisect run
+    #[argh(option)]
+    abort_codes: Option<CodeSet>,
+
+    /// what a script killed by a signal means: good, bad, skip or abort. Defaults to abort
+    #[argh(option)]
+    signal: Option<Outcome>,
+
+    /// kill runners after this many seconds, or after a multiple of the median runtime so far. Eg. "300" or "3x"
+    #[argh(option)]
+    timeout: Option<Timeout>,
+
+    /// what a runner that timed out means: good, bad, skip or abort. Defaults to skip
+    #[argh(option)]
+    timeout_status: Option<Outcome>,
+
+    /// print the last lines of the first bad commit's log when the bisection is done
+    #[argh(option)]
+    tail_log: Option<usize>,
+
+    /// what to clean before reusing a worktree for the next commit: none, untracked or all (including ignored files). Defaults to none, which keeps build artifacts
+    #[argh(option)]
+    clean: Option<Clean>,
+
+    /// the script is flaky. Test commits again until one is the first bad commit with this probability. Eg. "0.99"
+    #[argh(option)]
+    confidence: Option<f64>,
+
+    /// probability of a single result of a flaky script being wrong. Only used with --confidence. Defaults to 0.05
+    #[argh(option)]
+    flake_rate: Option<f64>,
+
+    /// resume an interrupted run from the state saved in .git/biasect
+    #[argh(switch)]
+    resume: bool,
+
     /// script to run, encapsulated in quotes. Eg. "make build"
     #[argh(positional)]
     script: String,
@@ -65,27 +131,229 @@ struct NextOptions {
     /// set the current working directory
     #[argh(option, short = 'C', default = "PathBuf::from(\".\")")]
     repo_path: PathBuf,
+
+    /// bias rule of the form "<glob>=<multiplier>". Eg. "*.c=2" or "docs/**=0". Can be repeated
+    #[argh(option, short = 'b')]
+    bias: Vec<BiasRule>,
+
+    /// allocator used to place runners: basic, weighted, probabilistic, runtime, speculative or kary. Defaults to weighted if any bias rules are given or configured
+    #[argh(option, short = 'a')]
+    allocator: Option<AllocatorKind>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum AllocatorKind {
+    Basic,
+    Weighted,
+    Probabilistic,
+    Runtime,
+    Speculative,
+    KAry,
+}
+
+impl FromStr for AllocatorKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "basic" => Ok(AllocatorKind::Basic),
+            "weighted" => Ok(AllocatorKind::Weighted),
+            "probabilistic" => Ok(AllocatorKind::Probabilistic),
+            "runtime" => Ok(AllocatorKind::Runtime),
+            "speculative" => Ok(AllocatorKind::Speculative),
+            "kary" => Ok(AllocatorKind::KAry),
+            _ => Err(format!(
+                "Unknown allocator `{s}`. Valid choices are basic, weighted, probabilistic, runtime, speculative, kary."
+            )),
+        }
+    }
+}
+
+/// Bias rules are useless to an allocator that ignores commit weights.
+fn allocator_kind(allocator: Option<AllocatorKind>, bias: &BiasConfig) -> AllocatorKind {
+    allocator.unwrap_or(if bias.is_empty() {
+        AllocatorKind::Basic
+    } else {
+        AllocatorKind::Weighted
+    })
+}
+
+/// Bias rules from the repository's config file, with rules given on the command line taking precedence.
+fn load_bias(repo_path: &Path, rules: &[BiasRule]) -> Result<BiasConfig, String> {
+    let mut config = BiasConfig::load(&get_repo_root(repo_path)?.join(CONFIG_FILE))?;
+    config.paths = rules.iter().cloned().chain(config.paths).collect();
+
+    Ok(config)
+}
+
+struct Runner {
+    /// Id of the runner's exit in the reaper
+    id: usize,
+    pid: u32,
+    commit: usize,
+    started: Instant,
+    /// Set once the runner was killed for running past this deadline
+    timed_out: Option<Duration>,
+    worktree: PathBuf,
 }
 
+/// Start a runner on each of `runner_commits`, adding them to `runners` as they start.
+/// Runners started before one fails to are kept, so they can be stopped.
 fn start_runners(
+    reaper: &mut Reaper,
+    worktrees: &mut Worktrees,
+    runners: &mut Vec<Runner>,
     runner_commits: &[usize],
     commits: &[String],
-    repo_path: &PathBuf,
     script_path: &str,
-) -> Vec<(usize, Child)> {
-    runner_commits
+    logs_dir: &Path,
+) -> Result<(), String> {
+    for commit_idx in runner_commits {
+        let (worktree, checkout) = worktrees.acquire(commits.get(*commit_idx).unwrap())?;
+        let child = run_script(
+            &checkout,
+            script_path,
+            &worktree,
+            &log_path(logs_dir, commits.get(*commit_idx).unwrap()),
+        )
+        .inspect_err(|_| worktrees.release(&worktree))?;
+        runners.push(Runner {
+            pid: child.id(),
+            id: reaper.watch(child),
+            commit: *commit_idx,
+            started: Instant::now(),
+            timed_out: None,
+            worktree,
+        });
+    }
+
+    Ok(())
+}
+
+/// A runner that was asked to stop with SIGTERM. Its whole process group gets SIGKILL once the grace period is over.
+struct Cancelled {
+    pid: u32,
+    worktree: PathBuf,
+    kill_at: Instant,
+    exited: bool,
+    killed: bool,
+}
+
+impl Cancelled {
+    fn new(runner: &Runner) -> Result<Self, String> {
+        kill_process_group(runner.pid, libc::SIGTERM)
+            .map_err(|err| format!("Failed to terminate runner: {err}"))?;
+
+        Ok(Cancelled {
+            pid: runner.pid,
+            worktree: runner.worktree.clone(),
+            kill_at: Instant::now() + KILL_GRACE_PERIOD,
+            exited: false,
+            killed: false,
+        })
+    }
+}
+
+/// SIGKILL cancelled runners past their grace period, and give back the worktrees of those that are gone for good.
+/// The group is killed even if its leader exited on SIGTERM, in case anything it spawned didn't.
+/// A group that's empty once its leader exited doesn't need to be waited on.
+fn reap_cancelled(
+    cancelled: &mut HashMap<usize, Cancelled>,
+    worktrees: &mut Worktrees,
+) -> Result<(), String> {
+    for runner in cancelled.values_mut() {
+        if !runner.killed && runner.kill_at <= Instant::now() {
+            kill_process_group(runner.pid, libc::SIGKILL)
+                .map_err(|err| format!("Failed to kill runner: {err}"))?;
+            runner.killed = true;
+        }
+    }
+
+    let gone = cancelled
         .iter()
-        .map(|commit_idx| {
-            (
-                *commit_idx,
-                run_script(
-                    &fs::canonicalize(repo_path).unwrap(),
-                    script_path,
-                    commits.get(*commit_idx).unwrap(),
-                ),
-            )
+        .filter(|(_, runner)| {
+            runner.exited && (runner.killed || !process_group_exists(runner.pid).unwrap_or(true))
         })
-        .collect()
+        .map(|(id, _)| *id)
+        .collect::<Vec<_>>();
+    for id in gone {
+        worktrees.recycle(&cancelled.remove(&id).unwrap().worktree)?;
+    }
+
+    Ok(())
+}
+
+/// Stop every runner, wait for them to exit and remove all worktrees.
+/// Runners get the grace period to exit on SIGTERM, or less if we're interrupted again.
+fn stop_runners(
+    runners: &[Runner],
+    mut cancelled: HashMap<usize, Cancelled>,
+    reaper: &Reaper,
+    worktrees: &mut Worktrees,
+) -> Result<(), String> {
+    // A runner that can't be signalled is left to finish on its own, the others still get stopped
+    let mut failed = Ok(());
+    for runner in runners {
+        match Cancelled::new(runner) {
+            Ok(stopping) => {
+                cancelled.insert(runner.id, stopping);
+            }
+            Err(err) => failed = Err(err),
+        }
+    }
+
+    let kill_at = Instant::now() + KILL_GRACE_PERIOD;
+    let mut running = cancelled
+        .iter()
+        .filter(|(_, runner)| !runner.exited)
+        .map(|(id, _)| *id)
+        .collect::<HashSet<_>>();
+    while !running.is_empty() {
+        match reaper.next(Some(kill_at)) {
+            Some(Event::Exited(reaped)) => {
+                running.remove(&reaped.id);
+            }
+            Some(Event::Signal(_)) | None => break,
+        }
+    }
+
+    for runner in cancelled.values() {
+        let _ = kill_process_group(runner.pid, libc::SIGKILL);
+    }
+    while !running.is_empty() {
+        if let Some(Event::Exited(reaped)) = reaper.next(None) {
+            running.remove(&reaped.id);
+        }
+    }
+
+    worktrees.remove_all()?;
+    failed
+}
+
+/// Stop every runner and remove the worktrees after `err` stopped the run, so failing leaves nothing behind.
+fn stop_on_error(
+    err: String,
+    runners: &[Runner],
+    cancelled: HashMap<usize, Cancelled>,
+    reaper: &Reaper,
+    worktrees: &mut Worktrees,
+    repo_path: &Path,
+) -> String {
+    let cleanup = stop_runners(runners, cancelled, reaper, worktrees)
+        .and_then(|()| worktree_prune(repo_path).map_err(String::from));
+    match cleanup {
+        Ok(()) => err,
+        Err(cleanup_err) => format!("{err}\n{cleanup_err}"),
+    }
+}
+
+/// Forget the saved state of a bisection whose bounds turned out to be wrong, so `--resume` can't pick it back up.
+fn bounds_invalid(state_path: &Path) -> String {
+    let err = "Initial bisection bounds invalid. Set them with `git bisect start <bad> <good>` and run again.";
+    match remove_state(state_path) {
+        Ok(()) => err.to_string(),
+        Err(remove_err) => format!("{err}\n{remove_err}"),
+    }
 }
 
 fn bounds_validated(commits: &[CommitState], reckless_mode: bool) -> bool {
@@ -98,240 +366,657 @@ fn bounds_validated(commits: &[CommitState], reckless_mode: bool) -> bool {
     commit_states.contains(&Status::Good) && commit_states.contains(&Status::Bad)
 }
 
-fn bisect_report_all(commits: &Vec<CommitState>, repo_path: &Path) {
-    for commit in commits {
-        if commit.status != Status::Unknown {
-            bisect_report(repo_path, &commit.status, &commit.hash);
-        }
+/// The in-process backend if it was compiled in, the `git` binary otherwise.
+fn open_backend(repo_path: &Path) -> Result<Box<dyn GitBackend>, String> {
+    #[cfg(feature = "libgit2")]
+    return Ok(Box::new(Libgit2Backend::open(repo_path)?));
+
+    #[cfg(not(feature = "libgit2"))]
+    return Ok(Box::new(CliBackend::new(repo_path)));
+}
+
+/// Mark every decided commit with `git bisect`, except the ones already in `git bisect log`. Returns how many were marked.
+fn bisect_report_all(commits: &[CommitState], backend: &dyn GitBackend) -> Result<usize, String> {
+    let reported = backend.bisect_log()?.into_iter().collect::<HashSet<_>>();
+    let is_reported =
+        |commit: &CommitState| reported.contains(&(commit.status, commit.hash.clone()));
+
+    // git moves its bad commit to whichever one is marked bad last, so the oldest has to be last.
+    // Marking a newer one than git has already would move it back.
+    let known_bad = commits
+        .iter()
+        .position(|commit| commit.status == Status::Bad && is_reported(commit))
+        .unwrap_or(commits.len());
+    let (bad, others): (Vec<_>, Vec<_>) = commits
+        .iter()
+        .enumerate()
+        .filter(|(_, commit)| commit.status != Status::Unknown && !is_reported(commit))
+        .filter(|(i, commit)| commit.status != Status::Bad || *i < known_bad)
+        .map(|(_, commit)| commit)
+        .partition(|commit| commit.status == Status::Bad);
+    let count = bad.len() + others.len();
+    for commit in others.into_iter().chain(bad.into_iter().rev()) {
+        backend.bisect_report(&commit.status, &commit.hash)?;
     }
+
+    Ok(count)
 }
 
-fn main() -> Result<(), String> {
-    let args: Args = argh::from_env();
+/// Commits in the bisection range, weighted by the bias rules and marked with the results already in `git bisect log`.
+fn get_weighted_commits(
+    backend: &dyn GitBackend,
+    bias: &BiasConfig,
+    check_bookends: bool,
+) -> Result<Vec<CommitState>, String> {
+    let mut metas = if bias.is_empty() {
+        HashMap::new()
+    } else {
+        backend.range_meta()?
+    };
+    let mut commits = backend
+        .commits()?
+        .into_iter()
+        .map(|hash| {
+            let meta = match metas.remove(&hash) {
+                Some(meta) => meta,
+                None if bias.is_empty() => CommitMeta::default(),
+                // The good commit the range starts after
+                None => backend.commit_meta(&hash)?,
+            };
+            Ok(CommitState::new(hash, meta))
+        })
+        .collect::<Result<Vec<_>, ShellError>>()?;
 
-    match args.subcommand {
-        SubCommands::Run(run_opts) => {
-            let commits = get_commits(&run_opts.repo_path)?;
-            let _files_per_commit = commits
+    // Parents outside of the bisection are known good, so they are left out
+    let index = commits
+        .iter()
+        .enumerate()
+        .map(|(i, x)| (x.hash.clone(), i))
+        .collect::<HashMap<_, _>>();
+    let mut parents = backend.range_parents()?;
+    for commit in commits.iter_mut() {
+        if let Some(hashes) = parents.remove(&commit.hash) {
+            commit.parents = hashes
                 .iter()
-                .map(|hash| get_commit_files(&run_opts.repo_path, hash).unwrap())
-                .collect::<Vec<_>>();
+                .filter_map(|x| index.get(x).copied())
+                .collect();
+        }
+    }
+
+    weigh_commits(&mut commits, bias);
+    mark_known(&mut commits, &backend.bisect_log()?, check_bookends);
+
+    Ok(commits)
+}
+
+/// Options given along with `--resume` have to match the saved run, whose results so far depend on them.
+fn check_resume_options(run_opts: &RunOptions, saved: &State) -> Result<(), String> {
+    if run_opts.reckless && saved.check_bookends {
+        return Err(
+            "The saved run checks the bounds. Resume it without --reckless, or run without --resume to start over."
+                .to_string(),
+        );
+    }
 
-            let mut state = init(&commits, run_opts.jobs, !run_opts.reckless);
-            let mut runners;
+    let Some(confidence) = run_opts.confidence else {
+        return Ok(());
+    };
+    match saved.flaky {
+        None => Err(
+            "The saved run trusts every result. Resume it without --confidence, or run without --resume to start over."
+                .to_string(),
+        ),
+        Some(flaky)
+            if flaky.confidence != confidence
+                || run_opts.flake_rate.is_some_and(|x| x != flaky.flake_rate) =>
+        {
+            Err(format!(
+                "The saved run uses --confidence {} --flake-rate {}. Resume it with those, or run without --resume to start over.",
+                flaky.confidence, flaky.flake_rate
+            ))
+        }
+        Some(_) => Ok(()),
+    }
+}
+
+/// Reload the state saved by an interrupted run, and catch up with what happened in `git bisect` since.
+fn resume_state<F>(
+    run_opts: &RunOptions,
+    backend: &dyn GitBackend,
+    state_path: &Path,
+) -> Result<State, String>
+where
+    F: Allocator,
+{
+    let saved = load_state(state_path)?;
+    check_resume_options(run_opts, &saved)?;
+
+    // The bisection range only ever shrinks, so a saved state of the same bisection covers it.
+    let saved_hashes = saved
+        .commits
+        .iter()
+        .map(|x| &x.hash)
+        .collect::<HashSet<_>>();
+    if !backend.commits()?.iter().all(|x| saved_hashes.contains(x)) {
+        return Err(format!(
+            "Saved state at `{}` does not match the bisection in progress. Run without --resume to start over.",
+            state_path.display()
+        ));
+    }
 
-            // Kick off runners
-            let start = Instant::now();
-            runners = start_runners(
+    Ok(resume::<F>(saved, &backend.bisect_log()?, run_opts.jobs))
+}
+
+/// The exit code options, with anything unset falling back to `git bisect run` semantics.
+fn exit_code_map(run_opts: &RunOptions) -> ExitCodeMap {
+    let default = ExitCodeMap::default();
+
+    ExitCodeMap {
+        skip: run_opts.skip_codes.clone().unwrap_or(default.skip),
+        abort: run_opts.abort_codes.clone().unwrap_or(default.abort),
+        signal: run_opts.signal.unwrap_or(default.signal),
+        timeout: run_opts.timeout_status.unwrap_or(default.timeout),
+    }
+}
+
+/// The flaky script options, if the script is flaky.
+fn flaky_options(run_opts: &RunOptions) -> Result<Option<Flaky>, String> {
+    match (run_opts.confidence, run_opts.flake_rate) {
+        (Some(confidence), flake_rate) => {
+            Ok(Some(Flaky::new(confidence, flake_rate.unwrap_or(0.05))?))
+        }
+        (None, Some(_)) => Err("--flake-rate is only used with --confidence".to_string()),
+        (None, None) => Ok(None),
+    }
+}
+
+/// Tell the user which commit is most likely the first bad commit of a flaky bisection.
+fn print_most_likely(commits: &[CommitState], flaky: &Flaky) {
+    let Some((index, probability)) = most_likely(commits) else {
+        return;
+    };
+    let reached = if probability >= flaky.confidence {
+        "reached"
+    } else {
+        "not reached"
+    };
+    println!(
+        "Commit `{}` is the first bad commit with probability {probability:.4}. Confidence {} {reached}.",
+        short_hash(&commits[index].hash),
+        flaky.confidence
+    );
+}
+
+/// Tell the user every commit the first bad commit could be, when skipped commits are in the way.
+fn print_skipped_suspects(commits: &[CommitState]) {
+    let skipped = skipped_suspects(commits);
+    if skipped.is_empty() {
+        return;
+    }
+
+    println!("Only skipped commits are left to test. The first bad commit could be any of:");
+    for x in skipped {
+        println!("{}", commits[x].hash);
+    }
+}
+
+/// Tell git and the user everything decided so far, after the bisection was interrupted.
+fn report_interrupted(state: &State, backend: &dyn GitBackend, signal: i32) -> Result<(), String> {
+    let commits = &state.commits;
+    // Until the bounds are confirmed, no result can be trusted to narrow anything down
+    if !bounds_validated(commits, !state.check_bookends) {
+        eprintln!(
+            "Bisection interrupted by signal {signal} before the bounds were confirmed. Nothing was marked.\n\
+            Continue with `git-biasect run --resume`"
+        );
+        return Ok(());
+    }
+    bisect_report_all(commits, backend)?;
+
+    print_commits(
+        commits
+            .iter()
+            .map(|x| x.status)
+            .collect::<Vec<_>>()
+            .as_slice(),
+        &[],
+    );
+
+    if commits.is_empty() {
+        eprintln!("Bisection interrupted by signal {signal}.");
+        return Ok(());
+    }
+
+    // Nothing is marked until the end, but some commit is the most likely so far
+    if let Some(flaky) = &state.flaky {
+        eprintln!("Bisection interrupted by signal {signal}.");
+        print_most_likely(commits, flaky);
+        eprintln!("Continue with `git-biasect run --resume`");
+        return Ok(());
+    }
+
+    // In a history without merges, that's the range and the bad commit right after it
+    let suspects = suspects(commits);
+    let (Some(first), Some(last)) = (suspects.first(), suspects.last()) else {
+        eprintln!("Bisection interrupted by signal {signal}.");
+        return Ok(());
+    };
+    eprintln!(
+        "Bisection interrupted by signal {signal}.\n\
+        The first bad commit is one of the {} commits from `{}` to `{}`.\n\
+        Continue with `git-biasect run --resume`",
+        suspects.len(),
+        short_hash(&commits[*first].hash),
+        short_hash(&commits[*last].hash)
+    );
+    Ok(())
+}
+
+/// Show why the first bad commit failed.
+fn print_first_bad_log(
+    commits: &[CommitState],
+    logs_dir: &Path,
+    lines: usize,
+) -> Result<(), String> {
+    let Some(first_bad) = commits.iter().find(|x| x.status == Status::Bad) else {
+        println!("No bad commit found, no log to show");
+        return Ok(());
+    };
+
+    let path = log_path(logs_dir, &first_bad.hash);
+    if !path.exists() {
+        // Bookends can be marked bad without ever being run
+        println!(
+            "Commit `{}` was never run, no log to show",
+            short_hash(&first_bad.hash)
+        );
+        return Ok(());
+    }
+
+    println!(
+        "Last {lines} lines of `{}`:\n{}",
+        path.display(),
+        tail_log(&path, lines)?
+    );
+    Ok(())
+}
+
+fn run<F>(run_opts: &RunOptions, bias: &BiasConfig) -> Result<(), String>
+where
+    F: Allocator,
+{
+    let exit_code_map = exit_code_map(run_opts);
+    let git_dir = get_git_dir(&run_opts.repo_path)?;
+    let state_path = state_path(&git_dir);
+    let logs_dir = logs_dir(&git_dir);
+    let backend = open_backend(&run_opts.repo_path)?;
+    let flaky = flaky_options(run_opts)?;
+    let mut state = if run_opts.resume {
+        resume_state::<F>(run_opts, backend.as_ref(), &state_path)?
+    } else if let Some(flaky) = flaky {
+        // A flaky script can't tell us the bounds are wrong, so they are trusted
+        reset_logs(&logs_dir)?;
+        init_flaky(
+            get_weighted_commits(backend.as_ref(), bias, false)?,
+            run_opts.jobs,
+            flaky,
+        )
+    } else {
+        reset_logs(&logs_dir)?;
+        init::<F>(
+            get_weighted_commits(backend.as_ref(), bias, !run_opts.reckless)?,
+            run_opts.jobs,
+            !run_opts.reckless,
+        )
+    };
+    save_state(&state, &state_path)?;
+
+    let commits = state
+        .commits
+        .iter()
+        .map(|x| x.hash.clone())
+        .collect::<Vec<_>>();
+    let mut runners = Vec::new();
+    let mut reaper = Reaper::new();
+    let mut worktrees = Worktrees::new(
+        &fs::canonicalize(&run_opts.repo_path).map_err(|err| {
+            format!(
+                "Failed to resolve `{}`: {err}",
+                run_opts.repo_path.display()
+            )
+        })?,
+        run_opts.clean.unwrap_or_default(),
+    );
+    // Invalidated runners that haven't been killed for good yet
+    let mut cancelled = HashMap::new();
+    reaper
+        .catch_signals()
+        .map_err(|err| format!("Failed to catch signals: {err}"))?;
+
+    let start = Instant::now();
+    // Runners are started from here on. Every error stops them and removes the worktrees before it's returned.
+    let mut run_to_end = || -> Result<(), String> {
+        // Kick off runners
+        start_runners(
+            &mut reaper,
+            &mut worktrees,
+            &mut runners,
+            &state.runners.commits,
+            &commits,
+            &run_opts.script,
+            &logs_dir,
+        )?;
+
+        loop {
+            print_commits(
+                state
+                    .commits
+                    .iter()
+                    .map(|x| x.status)
+                    .collect::<Vec<_>>()
+                    .as_slice(),
                 &state.runners.commits,
-                &commits,
-                &run_opts.repo_path,
-                &run_opts.script,
             );
 
-            let mut loop_iter = 0;
-            loop {
-                loop_iter += 1;
-                let mut rng = StdRng::seed_from_u64(loop_iter);
+            // Wait for the first completed child, killing runners that run out of time
+            let deadline = run_opts
+                .timeout
+                .and_then(|timeout| timeout.deadline(&state.runtime_samples));
 
-                print_commits(
-                    state
-                        .commits
+            let first_completed = loop {
+                let next_timeout = deadline.and_then(|deadline| {
+                    runners
                         .iter()
-                        .map(|x| x.status)
-                        .collect::<Vec<_>>()
-                        .as_slice(),
-                    &state.runners.commits,
-                );
+                        .filter(|runner| runner.timed_out.is_none())
+                        .map(|runner| runner.started + deadline)
+                        .min()
+                });
+                let next_kill = cancelled
+                    .values()
+                    .filter(|runner: &&Cancelled| !runner.killed)
+                    .map(|runner| runner.kill_at)
+                    .min();
+                let next_deadline = next_timeout.into_iter().chain(next_kill).min();
+
+                match reaper.next(next_deadline) {
+                    Some(Event::Exited(reaped)) => {
+                        // Runners cancelled after being invalidated still report their exit
+                        if let Some(runner) = cancelled.get_mut(&reaped.id) {
+                            runner.exited = true;
+                            reap_cancelled(&mut cancelled, &mut worktrees)?;
+                            continue;
+                        }
+                        let Some(runner_idx) =
+                            runners.iter().position(|runner| runner.id == reaped.id)
+                        else {
+                            continue;
+                        };
+                        let runner = runners.swap_remove(runner_idx);
+                        if runner.timed_out.is_some() {
+                            worktrees.recycle(&runner.worktree)?;
+                        } else {
+                            worktrees.release(&runner.worktree);
+                        }
+
+                        let exit_status = reaped
+                            .status
+                            .map_err(|err| format!("Failed to wait on runner: {err}"))?;
+                        let exit = match runner.timed_out {
+                            Some(deadline) => Exit::Timeout(deadline),
+                            None => Exit::from(exit_status),
+                        };
+                        break (runner.commit, exit, runner.started);
+                    }
+                    Some(Event::Signal(signal)) => {
+                        // Stop scheduling, keep what we learned
+                        stop_runners(&runners, mem::take(&mut cancelled), &reaper, &mut worktrees)?;
+                        worktree_prune(&run_opts.repo_path)?;
+                        report_interrupted(&state, backend.as_ref(), signal)?;
+
+                        // Like a shell, exit with 128 + the signal that stopped us
+                        process::exit(128 + signal);
+                    }
+                    None => {
+                        reap_cancelled(&mut cancelled, &mut worktrees)?;
 
-                // Wait for the first completed child
-                let mut first_completed = None;
-
-                // Runner count doesn't update
-                let runners_count = runners.len();
-                while first_completed.is_none() {
-                    for child in runners.iter_mut().choose_multiple(&mut rng, runners_count) {
-                        let res = child.1.try_wait();
-                        let res = res.unwrap();
-                        if let Some(exit_status) = res {
-                            first_completed = Some((child.0, exit_status));
+                        let Some(deadline) = deadline else {
+                            continue;
+                        };
+                        for runner in runners.iter_mut().filter(|runner| {
+                            runner.timed_out.is_none() && runner.started.elapsed() >= deadline
+                        }) {
+                            kill_process_group(runner.pid, libc::SIGKILL).map_err(|err| {
+                                format!("Failed to kill runner that timed out: {err}")
+                            })?;
+                            runner.timed_out = Some(deadline);
                         }
                     }
-                    // TODO: Replace with condvar or learn from the bisection script runtime to reduce compute burden
-                    sleep(Duration::from_secs(1));
                 }
+            };
 
-                let commit_index_exit_code = first_completed.unwrap();
-                let exit_code = commit_index_exit_code
-                    .1
-                    .code()
-                    .or_else(|| commit_index_exit_code.1.signal())
-                    .unwrap();
-                let exit_status = if exit_code == 0 {
-                    Status::Good
-                } else if exit_code == 124 {
-                    Status::Skip
-                } else {
-                    Status::Bad
-                };
-
-                // Check if result is invalid
-                // TODO: Nicer error messages that allow users to reproduce the failure with example commands
-                if commit_index_exit_code.0 == 0 && exit_status == Status::Bad {
-                    // The first commit must be good - that's what the user told us when setting up the bisection!
+            let commit_index_exit_code = first_completed;
+            let exit = commit_index_exit_code.1;
+            let exit_status = match exit_code_map.outcome(exit) {
+                Outcome::Mark(status) => status,
+                Outcome::Abort => {
                     eprintln!(
-                        "Initial bisection bounds invalid.\n\
-                        Commit: `{}` evaluated to bad with exit code {}.\n\
-                        The oldest commit must not be bad.\n\
+                        "Bisection aborted.\n\
+                        Commit: `{}` finished with {}, which aborts the bisection.\n\
+                        Output in `{}`\n\
                         \n\
                         Reproduce this failure with these commands:\n\
                         {}",
-                        commits.get(commit_index_exit_code.0).unwrap(),
-                        exit_code,
+                        short_hash(&commits[commit_index_exit_code.0]),
+                        exit,
+                        log_path(&logs_dir, commits.get(commit_index_exit_code.0).unwrap())
+                            .display(),
                         reproducer_shell_commands(
                             &run_opts.repo_path,
                             &run_opts.script,
                             &state.commits.get(commit_index_exit_code.0).unwrap().hash
                         )
                     );
-                    return Ok(());
-                } else if commit_index_exit_code.0 == commits.len() - 1
-                    && exit_status == Status::Good
-                {
-                    // The last commit must be bad - that's what the user told us when setting up the bisection!
-                    eprintln!(
-                        "Initial bisection bounds invalid.\n\
-                        Commit: `{}` evaluated to good with exit code {}.\n\
-                        The newest commit must not be good.\n\
-                        \n\
-                        Reproduce this failure with these commands:\n\
-                        {}",
-                        commits.get(commit_index_exit_code.0).unwrap(),
-                        exit_code,
-                        reproducer_shell_commands(
-                            &run_opts.repo_path,
-                            &run_opts.script,
-                            &state.commits.get(commit_index_exit_code.0).unwrap().hash
-                        )
-                    );
-                    return Ok(());
+
+                    return Err(format!(
+                        "Bisection aborted by {exit} on commit `{}`",
+                        short_hash(&commits[commit_index_exit_code.0])
+                    ));
                 }
+            };
 
-                let old_state = state;
-
-                // Perform step
-                let invalidated_runners;
-                let new_runners;
-                let current_runtime = start.elapsed().as_secs_f64();
-
-                let commit_runtime = current_runtime
-                    - *old_state
-                        .runners
-                        .start_times
-                        .get(
-                            old_state
-                                .runners
-                                .commits
-                                .iter()
-                                .enumerate()
-                                .filter(|(_, commit_idx)| commit_idx == &&commit_index_exit_code.0)
-                                .map(|(runner_idx, _)| runner_idx)
-                                .next()
-                                .unwrap(),
-                        )
-                        .unwrap();
-
-                (state, invalidated_runners, new_runners) = step::<BasicAllocator>(
-                    &old_state,
-                    exit_status,
-                    commit_index_exit_code.0,
-                    commit_runtime,
-                    current_runtime,
+            // Check if result is invalid
+            // TODO: Nicer error messages that allow users to reproduce the failure with example commands
+            if commit_index_exit_code.0 == 0 && exit_status == Status::Bad {
+                // The first commit must be good - that's what the user told us when setting up the bisection!
+                eprintln!(
+                    "Initial bisection bounds invalid.\n\
+                    Commit: `{}` evaluated to bad with {}.\n\
+                    The oldest commit must not be bad.\n\
+                    \n\
+                    Reproduce this failure with these commands:\n\
+                    {}",
+                    short_hash(&commits[commit_index_exit_code.0]),
+                    exit,
+                    reproducer_shell_commands(
+                        &run_opts.repo_path,
+                        &run_opts.script,
+                        &state.commits.get(commit_index_exit_code.0).unwrap().hash
+                    )
                 );
-
-                // Report status to git after ensuring bounds are valid
-                if bounds_validated(&state.commits, run_opts.reckless)
-                    && (commit_index_exit_code.0 == 0
-                        || commit_index_exit_code.0 == state.commits.len() - 1)
-                {
-                    // Report all bisection steps that have completed while validating the bounds
-                    println!(
-                        "Bounds newly validated, reporting commits {:?}",
-                        state.commits
-                    );
-                    bisect_report_all(&state.commits, &run_opts.repo_path);
-                } else if bounds_validated(&state.commits, run_opts.reckless) {
-                    // Report all bisection steps right away when bounds are validated
-                    bisect_report(
+                return Err(bounds_invalid(&state_path));
+            } else if commit_index_exit_code.0 == commits.len() - 1 && exit_status == Status::Good {
+                // The last commit must be bad - that's what the user told us when setting up the bisection!
+                eprintln!(
+                    "Initial bisection bounds invalid.\n\
+                    Commit: `{}` evaluated to good with {}.\n\
+                    The newest commit must not be good.\n\
+                    \n\
+                    Reproduce this failure with these commands:\n\
+                    {}",
+                    short_hash(&commits[commit_index_exit_code.0]),
+                    exit,
+                    reproducer_shell_commands(
                         &run_opts.repo_path,
-                        &exit_status,
-                        commits.get(commit_index_exit_code.0).unwrap(),
-                    );
-                }
-
-                // Cancel invalidated tasks
-                // TODO: Clean up temp folders
-                let _ = old_state
-                    .runners
-                    .commits
-                    .iter()
-                    .filter(|commit_idx| invalidated_runners.contains(commit_idx))
-                    .map(|commit_idx| {
-                        let mut invalidated_runners = runners
-                            .iter_mut()
-                            .filter(|x| x.0 == *commit_idx)
-                            .collect::<Vec<_>>();
-
-                        for invalidated_runners in invalidated_runners.iter_mut() {
-                            // println!("Killing {}", invalidated_runners.0);
-                            let killed = invalidated_runners.1.kill();
-                            if killed.is_ok() {
-                                // println!("Successfully cancelled {}", invalidated_runners.0);
-                            } else {
-                                panic!("Failed to kill invalidated runner: {:?}", killed.err());
-                            }
-                        }
-                    })
-                    .collect::<Vec<_>>();
-
-                let e_runners = runners
-                    .into_iter()
-                    .filter(|commit| {
-                        !(invalidated_runners.contains(&commit.0)
-                            || commit_index_exit_code.0 == commit.0)
-                    })
-                    .collect::<Vec<_>>();
-
-                let n_runners = start_runners(
-                    &new_runners,
-                    &commits,
-                    &run_opts.repo_path,
-                    &run_opts.script,
+                        &run_opts.script,
+                        &state.commits.get(commit_index_exit_code.0).unwrap().hash
+                    )
                 );
+                return Err(bounds_invalid(&state_path));
+            }
 
-                runners = e_runners.into_iter().chain(n_runners).collect();
+            println!(
+                "Commit `{}` is {exit_status:?} ({exit}). Output in `{}`",
+                short_hash(&commits[commit_index_exit_code.0]),
+                log_path(&logs_dir, commits.get(commit_index_exit_code.0).unwrap()).display()
+            );
 
-                if runners.is_empty() {
-                    break;
-                }
-            }
+            // Perform step
+            let invalidated_runners;
+            let new_runners;
+            let current_runtime = start.elapsed().as_secs_f64();
+            let commit_runtime = commit_index_exit_code.2.elapsed().as_secs_f64();
 
-            print_commits(
-                state
-                    .commits
-                    .iter()
-                    .map(|x| x.status)
-                    .collect::<Vec<_>>()
-                    .as_slice(),
-                &state.runners.commits,
+            (state, invalidated_runners, new_runners) = step::<F>(
+                &state,
+                exit_status,
+                commit_index_exit_code.0,
+                commit_runtime,
+                current_runtime,
             );
+            save_state(&state, &state_path)?;
+
+            // Report status to git after ensuring bounds are valid
+            if state.flaky.is_some() {
+                // Any single result may be wrong, so nothing is reported until one commit is likely enough
+            } else if bounds_validated(&state.commits, !state.check_bookends)
+                && (commit_index_exit_code.0 == 0
+                    || commit_index_exit_code.0 == state.commits.len() - 1)
+            {
+                // Report all bisection steps that have completed while validating the bounds
+                let reported = bisect_report_all(&state.commits, backend.as_ref())?;
+                println!("Bounds newly validated, reported {reported} commits");
+            } else if bounds_validated(&state.commits, !state.check_bookends) {
+                // Report all bisection steps right away when bounds are validated
+                backend
+                    .bisect_report(&exit_status, commits.get(commit_index_exit_code.0).unwrap())?;
+            }
+
+            // Cancel invalidated tasks
+            for runner in runners
+                .iter()
+                .filter(|runner| invalidated_runners.contains(&runner.commit))
+            {
+                let killed = Cancelled::new(runner)
+                    .map_err(|err| format!("Failed to cancel invalidated runner: {err}"))?;
+                cancelled.insert(runner.id, killed);
+            }
+            runners.retain(|runner| {
+                !(invalidated_runners.contains(&runner.commit)
+                    || commit_index_exit_code.0 == runner.commit)
+            });
+
+            start_runners(
+                &mut reaper,
+                &mut worktrees,
+                &mut runners,
+                &new_runners,
+                &commits,
+                &run_opts.script,
+                &logs_dir,
+            )?;
 
-            let _ = worktree_prune(&run_opts.repo_path).wait();
+            if runners.is_empty() {
+                break;
+            }
+        }
+
+        print_commits(
+            state
+                .commits
+                .iter()
+                .map(|x| x.status)
+                .collect::<Vec<_>>()
+                .as_slice(),
+            &state.runners.commits,
+        );
+
+        if let Some(flaky) = &state.flaky {
+            print_most_likely(&state.commits, flaky);
+            bisect_report_all(&state.commits, backend.as_ref())?;
+        }
+
+        print_skipped_suspects(&state.commits);
+
+        if let Some(lines) = run_opts.tail_log {
+            print_first_bad_log(&state.commits, &logs_dir, lines)?;
+        }
+
+        remove_state(&state_path)?;
+        // Runners cancelled in the last step may still be shutting down
+        stop_runners(&[], mem::take(&mut cancelled), &reaper, &mut worktrees)?;
+        worktree_prune(&run_opts.repo_path)?;
+
+        Ok(())
+    };
+    run_to_end().map_err(|err| {
+        stop_on_error(
+            err,
+            &runners,
+            cancelled,
+            &reaper,
+            &mut worktrees,
+            &run_opts.repo_path,
+        )
+    })
+}
+
+fn main() -> ExitCode {
+    let args: Args = argh::from_env();
+
+    match try_main(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn try_main(args: Args) -> Result<(), String> {
+    match args.subcommand {
+        SubCommands::Run(run_opts) => {
+            let bias = load_bias(&run_opts.repo_path, &run_opts.bias)?;
+
+            match allocator_kind(run_opts.allocator, &bias) {
+                AllocatorKind::Basic => run::<BasicAllocator>(&run_opts, &bias)?,
+                AllocatorKind::Weighted => run::<WeightedAllocator>(&run_opts, &bias)?,
+                AllocatorKind::Probabilistic => run::<ProbabilisticAllocator>(&run_opts, &bias)?,
+                AllocatorKind::Runtime => run::<RuntimeAllocator>(&run_opts, &bias)?,
+                AllocatorKind::Speculative => run::<SpeculativeAllocator>(&run_opts, &bias)?,
+                AllocatorKind::KAry => run::<KAryAllocator>(&run_opts, &bias)?,
+            }
         }
         SubCommands::Next(next_opts) => {
-            let commits = get_commits(&next_opts.repo_path)?;
+            let bias = load_bias(&next_opts.repo_path, &next_opts.bias)?;
+            let backend = open_backend(&next_opts.repo_path)?;
+            let commits = get_weighted_commits(backend.as_ref(), &bias, next_opts.check_bounds)?;
 
-            let state = init(&commits, 1, next_opts.check_bounds);
+            let state = match allocator_kind(next_opts.allocator, &bias) {
+                AllocatorKind::Basic => init::<BasicAllocator>(commits, 1, next_opts.check_bounds),
+                AllocatorKind::Weighted => {
+                    init::<WeightedAllocator>(commits, 1, next_opts.check_bounds)
+                }
+                AllocatorKind::Probabilistic => {
+                    init::<ProbabilisticAllocator>(commits, 1, next_opts.check_bounds)
+                }
+                AllocatorKind::Runtime => {
+                    init::<RuntimeAllocator>(commits, 1, next_opts.check_bounds)
+                }
+                AllocatorKind::Speculative => {
+                    init::<SpeculativeAllocator>(commits, 1, next_opts.check_bounds)
+                }
+                AllocatorKind::KAry => init::<KAryAllocator>(commits, 1, next_opts.check_bounds),
+            };
 
             print_commits(
                 state
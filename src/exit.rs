@@ -0,0 +1,126 @@
+/// Interpret how the bisection script exited.
+use std::{
+    fmt, ops::RangeInclusive, os::unix::process::ExitStatusExt, process::ExitStatus, str::FromStr,
+    time::Duration,
+};
+
+use crate::Status;
+
+/// How a runner's script finished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exit {
+    Code(i32),
+    Signal(i32),
+    /// Killed by us after running for too long.
+    Timeout(Duration),
+}
+
+impl From<ExitStatus> for Exit {
+    fn from(status: ExitStatus) -> Self {
+        match (status.code(), status.signal()) {
+            (Some(code), _) => Exit::Code(code),
+            (None, Some(signal)) => Exit::Signal(signal),
+            (None, None) => unreachable!("Process exited without an exit code or signal"),
+        }
+    }
+}
+
+impl fmt::Display for Exit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Exit::Code(code) => write!(f, "exit code {code}"),
+            Exit::Signal(signal) => write!(f, "signal {signal}"),
+            Exit::Timeout(deadline) => write!(f, "a timeout after {:.1}s", deadline.as_secs_f64()),
+        }
+    }
+}
+
+/// What a runner's result means for the bisection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Mark(Status),
+    /// Stop the whole bisection. Something is wrong with the script, not the commit.
+    Abort,
+}
+
+impl FromStr for Outcome {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "good" => Ok(Outcome::Mark(Status::Good)),
+            "bad" => Ok(Outcome::Mark(Status::Bad)),
+            "skip" => Ok(Outcome::Mark(Status::Skip)),
+            "abort" => Ok(Outcome::Abort),
+            _ => Err(format!(
+                "Unknown outcome `{s}`. Valid choices are good, bad, skip, abort."
+            )),
+        }
+    }
+}
+
+/// Set of exit codes. Parsed from a comma separated list of codes and inclusive ranges, eg. `124,125,128-255`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeSet(Vec<RangeInclusive<i32>>);
+
+impl CodeSet {
+    pub fn contains(&self, code: i32) -> bool {
+        self.0.iter().any(|range| range.contains(&code))
+    }
+}
+
+impl FromStr for CodeSet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |code: &str| {
+            code.trim()
+                .parse::<i32>()
+                .map_err(|err| format!("Invalid exit code `{code}` in `{s}`: {err}"))
+        };
+
+        s.split(',')
+            .filter(|x| !x.trim().is_empty())
+            .map(|x| match x.split_once('-') {
+                Some((lower, upper)) => Ok(parse(lower)?..=parse(upper)?),
+                None => parse(x).map(|code| code..=code),
+            })
+            .collect::<Result<_, _>>()
+            .map(CodeSet)
+    }
+}
+
+/// Maps a script's exit to an outcome. Defaults to the semantics of `git bisect run`:
+/// 0 is good, 125 is skip, 128 and above aborts and everything else is bad.
+/// Signals are kept apart from exit codes and abort by default. Timeouts skip by default.
+#[derive(Debug, Clone)]
+pub struct ExitCodeMap {
+    pub skip: CodeSet,
+    pub abort: CodeSet,
+    pub signal: Outcome,
+    pub timeout: Outcome,
+}
+
+impl Default for ExitCodeMap {
+    fn default() -> Self {
+        ExitCodeMap {
+            skip: CodeSet(vec![125..=125]),
+            abort: CodeSet(vec![128..=i32::MAX, i32::MIN..=-1]),
+            signal: Outcome::Abort,
+            timeout: Outcome::Mark(Status::Skip),
+        }
+    }
+}
+
+impl ExitCodeMap {
+    pub fn outcome(&self, exit: Exit) -> Outcome {
+        match exit {
+            Exit::Signal(_) => self.signal,
+            Exit::Timeout(_) => self.timeout,
+            Exit::Code(0) => Outcome::Mark(Status::Good),
+            Exit::Code(code) if self.skip.contains(code) => Outcome::Mark(Status::Skip),
+            Exit::Code(code) if self.abort.contains(code) => Outcome::Abort,
+            Exit::Code(_) => Outcome::Mark(Status::Bad),
+        }
+    }
+}
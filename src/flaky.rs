@@ -0,0 +1,117 @@
+/// Bisection with a script that sometimes gives the wrong result.
+/// Results don't mark commits. Each one updates the probability of every commit being the first bad commit instead.
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    alloc::{is_linear, mark_ancestors},
+    CommitState, Status,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Flaky {
+    /// Probability of a single result being wrong, good for bad or bad for good
+    pub flake_rate: f64,
+    /// Probability the first bad commit needs to reach to end the bisection
+    pub confidence: f64,
+}
+
+impl Flaky {
+    pub fn new(confidence: f64, flake_rate: f64) -> Result<Self, String> {
+        if !(confidence > 0.0 && confidence < 1.0) {
+            return Err(format!(
+                "Invalid confidence `{confidence}`. Expected a probability between 0 and 1, eg. `0.99`"
+            ));
+        }
+        if !(flake_rate > 0.0 && flake_rate < 0.5) {
+            return Err(format!(
+                "Invalid flake rate `{flake_rate}`. Expected a probability between 0 and 0.5, eg. `0.05`"
+            ));
+        }
+
+        Ok(Flaky {
+            flake_rate,
+            confidence,
+        })
+    }
+
+    /// Update the weight of every commit with the result of testing `index`.
+    pub fn observe(&self, commits: &mut [CommitState], index: usize, status: Status) {
+        let bad = match status {
+            Status::Bad => true,
+            Status::Good => false,
+            Status::Skip | Status::Unknown => return,
+        };
+
+        let bad_if_first = bad_if_first_bad(commits, index);
+        for (commit, bad_if_first) in commits.iter_mut().zip(bad_if_first) {
+            commit.weight *= if bad_if_first == bad {
+                1.0 - self.flake_rate
+            } else {
+                self.flake_rate
+            };
+        }
+        normalize(commits);
+    }
+
+    /// The commit that is the first bad commit with at least the requested confidence.
+    pub fn decided(&self, commits: &[CommitState]) -> Option<usize> {
+        most_likely(commits)
+            .filter(|(_, probability)| *probability >= self.confidence)
+            .map(|(x, _)| x)
+    }
+}
+
+/// For every commit, whether `index` is bad if that commit is the first bad commit.
+fn bad_if_first_bad(commits: &[CommitState], index: usize) -> Vec<bool> {
+    if is_linear(commits) {
+        return (0..commits.len()).map(|x| x <= index).collect();
+    }
+
+    let mut ancestors = vec![false; commits.len()];
+    mark_ancestors(commits, index, &mut ancestors);
+    ancestors
+}
+
+fn normalize(commits: &mut [CommitState]) {
+    let total: f64 = commits.iter().map(|x| x.weight).sum();
+    if total > 0.0 {
+        for commit in commits.iter_mut() {
+            commit.weight /= total;
+        }
+    }
+}
+
+/// Take marks that are known for sure into account, like the bounds of the bisection and `git bisect log`.
+/// The good commit the range starts after, ancestors of good commits, and commits that aren't ancestors of a bad commit
+/// can't be the first bad commit.
+pub fn condition_on_known(commits: &mut [CommitState]) {
+    if let Some(first) = commits.first_mut() {
+        first.weight = 0.0;
+    }
+
+    let known = commits
+        .iter()
+        .enumerate()
+        .filter(|(_, x)| matches!(x.status, Status::Good | Status::Bad))
+        .map(|(i, x)| (i, x.status))
+        .collect::<Vec<_>>();
+    for (index, status) in known {
+        let bad_if_first = bad_if_first_bad(commits, index);
+        for (commit, bad_if_first) in commits.iter_mut().zip(bad_if_first) {
+            if bad_if_first != (status == Status::Bad) {
+                commit.weight = 0.0;
+            }
+        }
+    }
+
+    normalize(commits);
+}
+
+/// The commit most likely to be the first bad commit, and how likely it is.
+pub fn most_likely(commits: &[CommitState]) -> Option<(usize, f64)> {
+    commits
+        .iter()
+        .map(|x| x.weight)
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+}
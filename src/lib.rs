@@ -1,9 +1,23 @@
 pub mod alloc;
+pub mod backend;
+pub mod bias;
+pub mod exit;
+pub mod flaky;
+pub mod persist;
+pub mod reaper;
 pub mod shell;
 pub mod tests;
+pub mod timeout;
 pub mod visualize;
+pub mod worktree;
 
-#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash)]
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::flaky::Flaky;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash, Serialize, Deserialize)]
 pub enum Status {
     Good,
     Bad,
@@ -11,13 +25,52 @@ pub enum Status {
     Unknown,
 }
 
-#[derive(Debug, Clone)]
+/// What we know about a commit besides its hash. Used to bias the bisection.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CommitMeta {
+    /// Paths touched by the commit
+    pub files: Vec<String>,
+    pub author: Option<String>,
+    /// First line of the commit message
+    pub subject: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CommitState {
     pub hash: String,
     pub status: Status,
+    /// Prior probability of this commit being the first bad commit
+    pub weight: f64,
+    /// Shared between every State in a bisection, so stepping doesn't copy it.
+    pub meta: Arc<CommitMeta>,
+    /// Indexes of the parents that are part of the bisection. They always come before the commit.
+    /// Empty for every commit of a history without merges that was built by hand.
+    #[serde(default)]
+    pub parents: Arc<[usize]>,
+}
+
+impl CommitState {
+    /// Unknown, unbiased commit.
+    pub fn new(hash: String, meta: CommitMeta) -> Self {
+        CommitState {
+            hash,
+            status: Status::Unknown,
+            weight: 1.0,
+            meta: Arc::new(meta),
+            parents: Arc::new([]),
+        }
+    }
+}
+
+/// Hashes are abbreviated to this many characters for display, like `git log --abbrev=12`
+pub const SHORT_HASH_LEN: usize = 12;
+
+/// Abbreviated hash, for display only. Everything else uses full hashes, which can't become ambiguous.
+pub fn short_hash(hash: &str) -> &str {
+    &hash[..hash.len().min(SHORT_HASH_LEN)]
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Runners {
     /// Runner to commit mapping
     pub commits: Vec<usize>,
@@ -26,10 +79,13 @@ pub struct Runners {
     pub total: usize,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct State {
     pub runtime_samples: Vec<f64>,
     pub commits: Vec<CommitState>,
     pub runners: Runners,
     pub check_bookends: bool,
+    /// Set if results can be wrong. Weights are then updated with every result instead of marking commits.
+    #[serde(default)]
+    pub flaky: Option<Flaky>,
 }
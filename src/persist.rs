@@ -0,0 +1,86 @@
+/// Save and restore a bisection, so an interrupted `run` can be resumed.
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::State;
+
+/// Everything git-biasect keeps for the bisection in progress lives in `.git/biasect`.
+pub fn run_dir(git_dir: &Path) -> PathBuf {
+    git_dir.join("biasect")
+}
+
+pub fn state_path(git_dir: &Path) -> PathBuf {
+    run_dir(git_dir).join("state.json")
+}
+
+/// Output of every runner, one file per commit.
+pub fn logs_dir(git_dir: &Path) -> PathBuf {
+    run_dir(git_dir).join("logs")
+}
+
+pub fn log_path(logs_dir: &Path, hash: &str) -> PathBuf {
+    logs_dir.join(format!("{hash}.log"))
+}
+
+/// Start a bisection with a fresh log directory, so no log of an earlier bisection is mistaken for this one's.
+pub fn reset_logs(logs_dir: &Path) -> Result<(), String> {
+    match fs::remove_dir_all(logs_dir) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => {
+            return Err(format!("Failed to remove `{}`: {err}", logs_dir.display()));
+        }
+        _ => (),
+    }
+
+    fs::create_dir_all(logs_dir)
+        .map_err(|err| format!("Failed to create `{}`: {err}", logs_dir.display()))
+}
+
+/// The last `lines` lines of a log.
+pub fn tail_log(path: &Path, lines: usize) -> Result<String, String> {
+    let log =
+        fs::read(path).map_err(|err| format!("Failed to read `{}`: {err}", path.display()))?;
+    let log = String::from_utf8_lossy(&log);
+    let skip = log.lines().count().saturating_sub(lines);
+
+    Ok(log.lines().skip(skip).collect::<Vec<_>>().join("\n"))
+}
+
+/// Write the state to `path`. Written to a temp file first, so an interrupted save never leaves a truncated state behind.
+pub fn save_state(state: &State, path: &Path) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|err| format!("Failed to create `{}`: {err}", dir.display()))?;
+    }
+
+    let tmp_path = path.with_extension("json.tmp");
+    let serialized =
+        serde_json::to_vec(state).map_err(|err| format!("Failed to serialize state: {err}"))?;
+    fs::write(&tmp_path, serialized)
+        .map_err(|err| format!("Failed to write `{}`: {err}", tmp_path.display()))?;
+    fs::rename(&tmp_path, path)
+        .map_err(|err| format!("Failed to write `{}`: {err}", path.display()))
+}
+
+pub fn load_state(path: &Path) -> Result<State, String> {
+    let serialized = fs::read(path).map_err(|err| match err.kind() {
+        io::ErrorKind::NotFound => {
+            format!("No saved state at `{}`. Nothing to resume.", path.display())
+        }
+        _ => format!("Failed to read `{}`: {err}", path.display()),
+    })?;
+
+    serde_json::from_slice(&serialized)
+        .map_err(|err| format!("Saved state at `{}` is corrupt: {err}", path.display()))
+}
+
+/// Forget the saved state once the bisection is over.
+pub fn remove_state(path: &Path) -> Result<(), String> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => {
+            Err(format!("Failed to remove `{}`: {err}", path.display()))
+        }
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,104 @@
+/// Notice finished runners the moment they exit, instead of polling them.
+use std::{
+    io, mem,
+    process::{Child, ExitStatus},
+    ptr,
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::Instant,
+};
+
+/// A child that exited, tagged with the id it was watched under.
+#[derive(Debug)]
+pub struct Reaped {
+    pub id: usize,
+    pub status: io::Result<ExitStatus>,
+}
+
+/// Something the run loop has to react to.
+#[derive(Debug)]
+pub enum Event {
+    Exited(Reaped),
+    /// We were asked to stop, eg. with Ctrl-C.
+    Signal(i32),
+}
+
+/// Waits on every watched child from its own thread and reports each exit exactly once.
+pub struct Reaper {
+    next_id: usize,
+    sender: Sender<Event>,
+    receiver: Receiver<Event>,
+}
+
+impl Default for Reaper {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Reaper {
+            next_id: 0,
+            sender,
+            receiver,
+        }
+    }
+}
+
+impl Reaper {
+    pub fn new() -> Self {
+        Reaper::default()
+    }
+
+    /// Hand over `child` to a waiter thread. Returns the id its exit will be reported under.
+    pub fn watch(&mut self, mut child: Child) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let sender = self.sender.clone();
+        thread::spawn(move || {
+            let status = child.wait();
+            // The receiver lives as long as the reaper, only gone if we're shutting down
+            let _ = sender.send(Event::Exited(Reaped { id, status }));
+        });
+
+        id
+    }
+
+    /// Deliver SIGINT and SIGTERM as events instead of dying on them, so runners can be cleaned up.
+    /// Must be called before any other thread is started, since only threads started later inherit the signal mask.
+    pub fn catch_signals(&self) -> io::Result<()> {
+        let set = unsafe {
+            let mut set = mem::zeroed::<libc::sigset_t>();
+            libc::sigemptyset(&mut set);
+            libc::sigaddset(&mut set, libc::SIGINT);
+            libc::sigaddset(&mut set, libc::SIGTERM);
+            set
+        };
+
+        let err = unsafe { libc::pthread_sigmask(libc::SIG_BLOCK, &set, ptr::null_mut()) };
+        if err != 0 {
+            return Err(io::Error::from_raw_os_error(err));
+        }
+
+        let sender = self.sender.clone();
+        thread::spawn(move || loop {
+            let mut signal = 0;
+            if unsafe { libc::sigwait(&set, &mut signal) } == 0
+                && sender.send(Event::Signal(signal)).is_err()
+            {
+                break;
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Block until a watched child exits or a caught signal arrives. Gives up at `deadline`, if any.
+    pub fn next(&self, deadline: Option<Instant>) -> Option<Event> {
+        match deadline {
+            Some(deadline) => self
+                .receiver
+                .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                .ok(),
+            // We hold a sender ourselves, so this only returns once something happens
+            None => self.receiver.recv().ok(),
+        }
+    }
+}
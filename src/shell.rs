@@ -1,83 +1,369 @@
 /// Functions that invoke shell commands
 use std::{
-    path::Path,
-    process::{Child, Command},
+    collections::HashMap,
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufReader},
+    os::unix::process::CommandExt,
+    path::{Path, PathBuf},
+    process::{Child, Command, ExitStatus, Stdio},
 };
 
-use crate::Status;
-
-/// Get commits. Ordered from old to new.
-pub fn get_commits(repo_path: &Path) -> Result<Vec<String>, String> {
-    let cmd_git_bisect_log = Command::new("git")
-        .arg("-C")
-        .arg(repo_path.as_os_str().to_str().unwrap())
-        .arg("bisect")
-        .arg("visualize")
-        .arg("--oneline")
-        .arg("--reverse")
-        .output()
-        .unwrap();
-    let out = String::from_utf8(cmd_git_bisect_log.stdout).unwrap();
-    let lines = out.lines();
-    let hashes: Vec<Option<&str>> = lines.map(|s| s.split(' ').next()).collect();
-
-    if hashes.contains(&None) {
-        return Err(format!("Could not get commit range from output: '{out}'"));
+use crate::{short_hash, CommitMeta, Status};
+
+/// Everything that can go wrong running git or a runner.
+#[derive(Debug)]
+pub enum ShellError {
+    /// `git` isn't installed or not on the `PATH`.
+    GitNotFound,
+    /// The path isn't inside a git repository.
+    NotARepo(PathBuf),
+    /// `git bisect start` hasn't been run.
+    NoBisect,
+    /// The bisection has no commits to test, eg. because only one bound was given.
+    EmptyRange,
+    /// Paths end up in shell commands, which need them as UTF-8.
+    NonUtf8Path(PathBuf),
+    /// A command ran, but failed.
+    CommandFailed { command: String, stderr: String },
+    /// A command couldn't be started, or a file it needs couldn't be set up.
+    Io { context: String, err: io::Error },
+    /// Only good, bad and skip can be reported to `git bisect`.
+    UnknownStatus(String),
+    /// The in-process git backend failed.
+    #[cfg(feature = "libgit2")]
+    Git2(git2::Error),
+}
+
+impl fmt::Display for ShellError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShellError::GitNotFound => {
+                write!(f, "Could not run `git`. Is git installed and on the PATH?")
+            }
+            ShellError::NotARepo(path) => write!(
+                f,
+                "`{}` is not a git repository. Run git-biasect inside the repository or point to it with `-C <path>`.",
+                path.display()
+            ),
+            ShellError::NoBisect => write!(
+                f,
+                "No bisection in progress. Start one with `git bisect start <bad> <good>`."
+            ),
+            ShellError::EmptyRange => write!(
+                f,
+                "No hashes in bisection range. Did you set the bounds of your bisection with `git bisect good` and `git bisect bad`?"
+            ),
+            ShellError::NonUtf8Path(path) => {
+                write!(f, "Path `{}` is not valid UTF-8", path.display())
+            }
+            ShellError::CommandFailed { command, stderr } if stderr.trim().is_empty() => {
+                write!(f, "`{command}` failed")
+            }
+            ShellError::CommandFailed { command, stderr } => {
+                write!(f, "`{command}` failed:\n{}", stderr.trim_end())
+            }
+            ShellError::Io { context, err } => write!(f, "{context}: {err}"),
+            ShellError::UnknownStatus(hash) => write!(
+                f,
+                "Cannot report `{}` to git bisect while its status is unknown. Valid choices are good, bad, skip.",
+                short_hash(hash)
+            ),
+            #[cfg(feature = "libgit2")]
+            ShellError::Git2(err) => write!(f, "{}", err.message()),
+        }
+    }
+}
+
+impl std::error::Error for ShellError {}
+
+#[cfg(feature = "libgit2")]
+impl From<git2::Error> for ShellError {
+    fn from(err: git2::Error) -> Self {
+        ShellError::Git2(err)
+    }
+}
+
+impl From<ShellError> for String {
+    fn from(err: ShellError) -> Self {
+        err.to_string()
     }
+}
+
+fn path_str(path: &Path) -> Result<&str, ShellError> {
+    path.to_str()
+        .ok_or_else(|| ShellError::NonUtf8Path(path.to_path_buf()))
+}
+
+/// `git -C <repo_path>`
+fn git(repo_path: &Path) -> Result<Command, ShellError> {
+    let mut cmd = Command::new("git");
+    cmd.arg("-C").arg(path_str(repo_path)?);
+    Ok(cmd)
+}
+
+/// Human readable `cmd`, for error messages.
+fn describe(cmd: &Command) -> String {
+    [cmd.get_program()]
+        .into_iter()
+        .chain(cmd.get_args())
+        .map(|x| x.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn spawn_error(cmd: &Command, err: io::Error) -> ShellError {
+    if err.kind() == io::ErrorKind::NotFound && cmd.get_program() == "git" {
+        ShellError::GitNotFound
+    } else {
+        ShellError::Io {
+            context: format!("Failed to run `{}`", describe(cmd)),
+            err,
+        }
+    }
+}
 
-    let unwrapped_hashes: Vec<String> =
-        hashes.into_iter().map(|x| x.unwrap().to_string()).collect();
+/// Why `cmd` failed, going by what it printed to stderr.
+fn check_status(
+    cmd: &Command,
+    repo_path: &Path,
+    status: ExitStatus,
+    stderr: &[u8],
+) -> Result<(), ShellError> {
+    if status.success() {
+        return Ok(());
+    }
 
-    if unwrapped_hashes.is_empty() {
-        return Err("No hashes in bisection range. Did you set the bounds of your bisection with `git bisect good` and `git bisect bad`?".to_string());
+    let stderr = String::from_utf8_lossy(stderr).to_string();
+    if stderr.contains("not a git repository") || stderr.contains("cannot change to") {
+        return Err(ShellError::NotARepo(repo_path.to_path_buf()));
+    }
+    if stderr.contains("not bisecting") || stderr.contains("You need to start by") {
+        return Err(ShellError::NoBisect);
     }
+    Err(ShellError::CommandFailed {
+        command: describe(cmd),
+        stderr,
+    })
+}
+
+/// Run `cmd` to completion and return what it printed, or why it failed.
+/// Unless told otherwise, stdout and stderr are captured.
+fn output(cmd: &mut Command, repo_path: &Path) -> Result<String, ShellError> {
+    let output = cmd.output().map_err(|err| spawn_error(cmd, err))?;
+    check_status(cmd, repo_path, output.status, &output.stderr)?;
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// `git rev-list` over the bisection range, printing `format` for every commit.
+/// Lists nothing without a bad commit.
+fn bisect_rev_list(repo_path: &Path, format: &str) -> Result<Command, ShellError> {
+    let mut cmd = git(repo_path)?;
+    cmd.arg("rev-list")
+        .arg("--no-commit-header")
+        .arg(format!("--format={format}"))
+        .arg("--ignore-missing")
+        .arg("refs/bisect/bad")
+        .arg("--not")
+        .arg("--glob=refs/bisect/good-*");
+    Ok(cmd)
+}
+
+/// Get the full hashes of the commits in the bisection range. Ordered from old to new.
+/// The first commit is the good commit the range starts after.
+pub fn get_commits(repo_path: &Path) -> Result<Vec<String>, ShellError> {
+    let out = output(
+        bisect_rev_list(repo_path, "%H")?
+            .arg("--topo-order")
+            .arg("--reverse"),
+        repo_path,
+    )?;
 
     // This unwrapped_hashes has all hashes that could be or are known to be bad.
+    // Without a bad commit, nothing is listed.
+    let unwrapped_hashes: Vec<String> = out
+        .lines()
+        .filter(|x| !x.is_empty())
+        .map(|x| x.to_string())
+        .collect();
+
+    let Some(first_hash) = unwrapped_hashes.first() else {
+        // Without a bisection there are no refs either, so tell the two apart
+        if !get_git_dir(repo_path)?.join("BISECT_START").exists() {
+            return Err(ShellError::NoBisect);
+        }
+        return Err(ShellError::EmptyRange);
+    };
 
     // Get the hash that is one above the current range (known to be good).
-    let good_hash = {
-        // Get known good commit
-        let cmd_git_log = Command::new("git")
-            .arg("-C")
-            .arg(repo_path.as_os_str().to_str().unwrap())
-            .arg("log")
-            .arg("--oneline")
+    let good_hash = output(
+        git(repo_path)?
+            .arg("rev-list")
+            .arg("--no-commit-header")
+            .arg("--format=%H")
             .arg("-n")
             .arg("1")
-            .arg(format!("{}^", unwrapped_hashes.first().unwrap()))
-            .output()
-            .unwrap();
-
-        let out = String::from_utf8(cmd_git_log.stdout).unwrap();
-        let lines = out.lines();
-        let good_hash: Option<&str> = lines.map(|s| s.split(' ').next()).next().unwrap();
-
-        good_hash.unwrap().to_string()
-    };
+            .arg(format!("{first_hash}^")),
+        repo_path,
+    )?
+    .trim()
+    .to_string();
 
     let hashes = [vec![good_hash], unwrapped_hashes].concat();
 
     Ok(hashes)
 }
 
-pub fn get_commit_files(repo_path: &Path, hash: &str) -> Result<Vec<String>, String> {
-    let cmd_git_bisect_log = Command::new("git")
-        .arg("-C")
-        .arg(repo_path.as_os_str().to_str().unwrap())
-        .arg("diff-tree")
-        .arg("--no-commit-id")
+/// Get the author, subject and the files touched by a commit.
+pub fn get_commit_meta(repo_path: &Path, hash: &str) -> Result<CommitMeta, ShellError> {
+    let mut cmd = git(repo_path)?;
+    cmd.arg("log")
+        .arg("-n")
+        .arg("1")
+        .arg("-z")
+        .arg("--no-show-signature")
+        .arg(format!("--format={RANGE_META_FORMAT}"))
         .arg("--name-only")
-        .arg("-r")
-        .arg(hash)
-        .output()
-        .unwrap();
-    let out = String::from_utf8(cmd_git_bisect_log.stdout).unwrap();
-    let lines = out.lines();
-    let files: Vec<Option<&str>> = lines.map(|s| s.split(' ').next()).collect();
+        .arg(hash);
+    let out = output(&mut cmd, repo_path)?;
 
-    let unwrapped_files: Vec<String> = files.into_iter().map(|x| x.unwrap().to_string()).collect();
+    let metas = parse_range_meta(out.as_bytes()).map_err(|err| ShellError::Io {
+        context: format!("Failed to read the output of `{}`", describe(&cmd)),
+        err,
+    })?;
+    Ok(metas.into_values().next().unwrap_or_default())
+}
 
-    Ok(unwrapped_files)
+/// Starts every commit in the output of `git log -z --format=RANGE_META_FORMAT --name-only`.
+const RANGE_META_FORMAT: &str = "%x00%H%x00%an <%ae>%x00%s";
+
+/// Parse `git log -z --format=RANGE_META_FORMAT --name-only` field by field, as it's being printed.
+pub fn parse_range_meta(log: impl BufRead) -> io::Result<HashMap<String, CommitMeta>> {
+    let mut metas = HashMap::new();
+    let mut current: Option<(String, CommitMeta)> = None;
+    let mut fields = log
+        .split(b'\0')
+        .map(|field| field.map(|x| String::from_utf8_lossy(&x).into_owned()));
+
+    // \0<hash>\0<author>\0<subject>\0, followed by a newline and the changed files, each ending in \0.
+    // File names are printed as they are, so they can hold anything but \0.
+    while let Some(field) = fields.next().transpose()? {
+        if field.is_empty() {
+            metas.extend(current.take());
+
+            let hash = fields.next().transpose()?.unwrap_or_default();
+            current = Some((
+                hash,
+                CommitMeta {
+                    files: Vec::new(),
+                    author: fields.next().transpose()?,
+                    subject: fields.next().transpose()?,
+                },
+            ));
+        } else if let Some((_, meta)) = current.as_mut() {
+            // The newline only comes before the first file
+            let file = match field.strip_prefix('\n') {
+                Some(file) if meta.files.is_empty() => file.to_string(),
+                _ => field,
+            };
+            meta.files.push(file);
+        }
+    }
+    metas.extend(current);
+
+    Ok(metas)
+}
+
+/// Get author, subject and changed files of every commit in the bisection range, from a single `git log`.
+/// Excludes the good commit the range starts after.
+pub fn get_range_meta(repo_path: &Path) -> Result<HashMap<String, CommitMeta>, ShellError> {
+    let mut cmd = git(repo_path)?;
+    // NUL separated, so file names aren't quoted whatever `core.quotePath` says
+    cmd.arg("log")
+        .arg("--bisect")
+        .arg("-z")
+        .arg("--no-show-signature")
+        .arg(format!("--format={RANGE_META_FORMAT}"))
+        .arg("--name-only")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let mut child = cmd.spawn().map_err(|err| spawn_error(&cmd, err))?;
+
+    // Ranges can be huge, so parse the log as it streams in instead of buffering all of it
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let metas = parse_range_meta(BufReader::new(stdout));
+
+    let output = child.wait_with_output().map_err(|err| ShellError::Io {
+        context: format!("Failed to wait on `{}`", describe(&cmd)),
+        err,
+    })?;
+    check_status(&cmd, repo_path, output.status, &output.stderr)?;
+
+    metas.map_err(|err| ShellError::Io {
+        context: format!("Failed to read the output of `{}`", describe(&cmd)),
+        err,
+    })
+}
+
+/// Get the parents of every commit in the bisection range, from a single `git rev-list`.
+/// Excludes the good commit the range starts after.
+pub fn get_range_parents(repo_path: &Path) -> Result<HashMap<String, Vec<String>>, ShellError> {
+    let out = output(&mut bisect_rev_list(repo_path, "%H %P")?, repo_path)?;
+
+    // <hash> <parent>...
+    Ok(out
+        .lines()
+        .filter_map(|line| {
+            let mut hashes = line.split_whitespace().map(|x| x.to_string());
+            Some((hashes.next()?, hashes.collect()))
+        })
+        .collect())
+}
+
+/// Get the root of the repository's working tree.
+pub fn get_repo_root(repo_path: &Path) -> Result<PathBuf, ShellError> {
+    let out = output(
+        git(repo_path)?.arg("rev-parse").arg("--show-toplevel"),
+        repo_path,
+    )?;
+    Ok(PathBuf::from(out.trim()))
+}
+
+/// Get the absolute path of the repository's `.git` directory.
+pub fn get_git_dir(repo_path: &Path) -> Result<PathBuf, ShellError> {
+    let out = output(
+        git(repo_path)?.arg("rev-parse").arg("--absolute-git-dir"),
+        repo_path,
+    )?;
+    Ok(PathBuf::from(out.trim()))
+}
+
+/// Parse the commits marked by `git bisect log`. Returns full hashes in the order they were marked.
+pub fn parse_bisect_log(log: &str) -> Vec<(Status, String)> {
+    // Every marked commit has a comment, regardless of how it was referred to on the command line.
+    // # bad: [<hash>] <subject>
+    log.lines()
+        .filter_map(|line| line.strip_prefix("# "))
+        .filter_map(|line| line.split_once(": ["))
+        .filter_map(|(term, rest)| {
+            let status = match term {
+                "good" | "old" => Status::Good,
+                "bad" | "new" => Status::Bad,
+                "skip" => Status::Skip,
+                _ => return None,
+            };
+            let (hash, _) = rest.split_once(']')?;
+            Some((status, hash.to_string()))
+        })
+        .collect()
+}
+
+/// Get the commits marked during the bisection in progress.
+pub fn get_bisect_log(repo_path: &Path) -> Result<Vec<(Status, String)>, ShellError> {
+    let out = output(git(repo_path)?.arg("bisect").arg("log"), repo_path)?;
+    Ok(parse_bisect_log(&out))
 }
 
 pub fn reproducer_shell_commands(repo_path: &Path, command: &String, commit: &String) -> String {
@@ -88,81 +374,155 @@ pub fn reproducer_shell_commands(repo_path: &Path, command: &String, commit: &St
         git -C {} worktree add $TESTDIR {commit} --detach\n\
         {command}\n\
         echo $?",
-        repo_path.as_os_str().to_str().unwrap()
+        repo_path.display()
     )
 }
 
-pub fn run_script(repo_path: &Path, command: &str, commit: &String) -> Child {
-    let tempdir_cmd = Command::new("mktemp")
-        .arg("-d")
-        .arg("-t")
-        .arg("biasect.XXXXXX")
-        .output();
+/// git worktree add --detach --no-checkout <worktree> <commit>
+/// Only sets the worktree up, its files are checked out by the runner.
+pub fn worktree_add(repo_path: &Path, worktree: &Path, commit: &str) -> Result<(), ShellError> {
+    output(
+        git(repo_path)?
+            .arg("worktree")
+            .arg("add")
+            .arg("--detach")
+            .arg("--no-checkout")
+            .arg(worktree)
+            .arg(commit),
+        repo_path,
+    )?;
+    Ok(())
+}
 
-    if let Err(err) = tempdir_cmd {
-        panic!("Failed to create tempdir via `mktemp -d -t biasect`: {err}");
+/// Switch the worktree in the current directory to `commit`, throwing away changes to tracked files.
+/// `clean` are the flags of `git clean` to also remove untracked files, if any.
+pub fn worktree_switch_command(commit: &str, clean: Option<&str>) -> String {
+    let switch = format!("git checkout --quiet --force --detach {commit}");
+    match clean {
+        Some(flags) => format!("{switch} && git clean --quiet {flags}"),
+        None => switch,
     }
+}
 
-    let tempdir_cmd = tempdir_cmd.unwrap();
+/// Characters that need a shell to run a command, like git's `prepare_shell_cmd` checks for.
+const SHELL_METACHARACTERS: &[char] = &[
+    '|', '&', ';', '<', '>', '(', ')', '$', '`', '\\', '"', '\'', '*', '?', '[', '#', '~', '=',
+    '%', '\n',
+];
 
-    let tempdir = String::from_utf8_lossy(&tempdir_cmd.stdout)
-        .trim()
-        .to_string();
+/// Run `command` in `worktree`, after checking out the commit to test with `checkout`. Everything it prints goes to `log`.
+pub fn run_script(
+    checkout: &str,
+    command: &str,
+    worktree: &Path,
+    log: &Path,
+) -> Result<Child, ShellError> {
+    let log_error = |err| ShellError::Io {
+        context: format!("Failed to create log `{}`", log.display()),
+        err,
+    };
+    let stdout = File::create(log).map_err(log_error)?;
+    let stderr = stdout.try_clone().map_err(log_error)?;
 
     // Chained commands (&&) in plaintext. I wish there was an easier way to do this.
     // 1. Checkout worktree
-    // git worktree -C {repo_path} add {tempdir} {commit} --detach
-    // 2. Invoke command
-    Command::new("sh")
-        .arg("-c")
-        .arg(format!(
-            "git -C {} worktree add {tempdir} {commit} --detach && {command}",
-            repo_path.as_os_str().to_str().unwrap()
-        ))
-        .current_dir(tempdir)
-        .stdout(std::process::Stdio::null())
-        .stderr(std::process::Stdio::null())
-        .spawn()
-        .expect("Failed to execute script on hash") // Simulating script execution
+    // 2. Invoke command. A program without shell syntax replaces the shell, so a signal that kills it reaches us
+    //    as one. `sh` would report it as exit code 128 + the signal instead. Builtins can't be exec'd.
+    let mut cmd = Command::new("sh");
+    if command.contains(SHELL_METACHARACTERS) {
+        cmd.arg("-c").arg(format!("{checkout} && {command}"));
+    } else {
+        cmd.arg("-c")
+            .arg(format!(
+                "{checkout} && case $(command -v \"$1\") in */*) exec \"$@\" ;; *) \"$@\" ;; esac"
+            ))
+            .arg("sh")
+            .args(command.split_whitespace());
+    }
+    cmd.current_dir(worktree)
+        // Own process group, so everything the script spawns can be killed with it
+        .process_group(0)
+        .stdout(stdout)
+        .stderr(stderr);
+    cmd.spawn().map_err(|err| spawn_error(&cmd, err))
+}
+
+/// Send `signal` to a runner started by `run_script` and everything it spawned.
+/// A group that's already gone is not an error.
+pub fn kill_process_group(pid: u32, signal: libc::c_int) -> io::Result<()> {
+    // The runner leads its own process group, so its pid is the group id
+    if unsafe { libc::killpg(pid as libc::pid_t, signal) } == -1 {
+        let err = io::Error::last_os_error();
+        if err.raw_os_error() != Some(libc::ESRCH) {
+            return Err(err);
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether anything in the process group of a runner started by `run_script` is still around.
+pub fn process_group_exists(pid: u32) -> io::Result<bool> {
+    // Signal 0 only checks whether the group can be signalled
+    if unsafe { libc::killpg(pid as libc::pid_t, 0) } == -1 {
+        let err = io::Error::last_os_error();
+        if err.raw_os_error() == Some(libc::ESRCH) {
+            return Ok(false);
+        }
+        return Err(err);
+    }
+
+    Ok(true)
 }
 
 /// git bisect <good|bad|skip> <hash>
-pub fn bisect_report(repo_path: &Path, status: &Status, hash: &String) -> Child {
+pub fn bisect_report(repo_path: &Path, status: &Status, hash: &String) -> Result<(), ShellError> {
     let action = match status {
         Status::Good => "good",
         Status::Bad => "bad",
         Status::Skip => "skip",
-        Status::Unknown => {
-            panic!("Cannot report unknown state to git bisect. Valid choices are Good, Bad, Skip.")
-        }
+        Status::Unknown => return Err(ShellError::UnknownStatus(hash.clone())),
     };
 
-    Command::new("git")
-        .arg("-C")
-        .arg(repo_path)
-        .arg("bisect")
+    // Let git tell the user how the bisection is going
+    let mut cmd = git(repo_path)?;
+    cmd.arg("bisect")
         .arg(action)
         .arg(hash)
-        .current_dir(repo_path.as_os_str().to_str().unwrap())
-        .spawn()
-        .unwrap_or_else(|_| {
-            panic!(
-                "Failed to execute `git bisect {action} {hash}` at directory `{}`",
-                repo_path.as_os_str().to_str().unwrap()
-            )
-        })
+        .current_dir(repo_path)
+        // Never offer to start a bisection for us
+        .stdin(Stdio::null())
+        .stdout(Stdio::inherit());
+    let output = cmd.output().map_err(|err| spawn_error(&cmd, err))?;
+
+    // Only skipped commits are left to test, which git just listed
+    if output.status.code() == Some(2) {
+        return Ok(());
+    }
+    check_status(&cmd, repo_path, output.status, &output.stderr)
 }
 
-pub fn worktree_prune(repo_path: &Path) -> Child {
-    Command::new("git")
-        .arg("worktree")
-        .arg("prune")
-        .current_dir(repo_path.as_os_str().to_str().unwrap())
-        .spawn()
-        .unwrap_or_else(|_| {
-            panic!(
-                "Failed to execute `git worktree prune` at directory `{}`",
-                repo_path.as_os_str().to_str().unwrap()
-            )
-        })
+pub fn worktree_prune(repo_path: &Path) -> Result<(), ShellError> {
+    output(git(repo_path)?.arg("worktree").arg("prune"), repo_path)?;
+    Ok(())
+}
+
+/// git reset --hard, in the worktree at `path`. Throws away what a runner killed halfway through a checkout left
+/// of tracked files. Fails if it left the index locked.
+pub fn worktree_reset(path: &Path) -> Result<(), ShellError> {
+    output(git(path)?.arg("reset").arg("--hard").arg("--quiet"), path)?;
+    Ok(())
+}
+
+/// git worktree remove --force <path>
+pub fn worktree_remove(repo_path: &Path, path: &Path) -> Result<(), ShellError> {
+    output(
+        git(repo_path)?
+            .arg("worktree")
+            .arg("remove")
+            .arg("--force")
+            .arg(path),
+        repo_path,
+    )?;
+    Ok(())
 }
@@ -3,7 +3,7 @@ use rand_distr::{Distribution, Normal};
 
 use crate::{
     alloc::{get_range, init, step, Allocator},
-    State, Status,
+    CommitMeta, CommitState, State, Status,
 };
 
 fn generate_runtime_for_commits(
@@ -30,7 +30,7 @@ fn get_next_result(
     *runners
         .iter()
         .zip(runner_start_times)
-        .map(|(commit, start)| (commit, commit_runtimes.get(*commit).unwrap() - start))
+        .map(|(commit, start)| (commit, start + commit_runtimes.get(*commit).unwrap()))
         .min_by(|x, y| x.1.total_cmp(&y.1))
         .unwrap()
         .0
@@ -52,12 +52,14 @@ where
     let mut total_runtime = 0.0;
     let mut total_steps = 0;
     for seed in 0..iters {
-        let commits: Vec<_> = (0..commit_count).map(|n| n.to_string()).collect();
+        let commits: Vec<_> = (0..commit_count)
+            .map(|n| CommitState::new(n.to_string(), CommitMeta::default()))
+            .collect();
 
         let commit_runtimes =
             generate_runtime_for_commits(commits.len(), runtime_mean, runtime_stddev);
 
-        let mut state: State = init(&commits, runners, check_bookends);
+        let mut state: State = init::<F>(commits, runners, check_bookends);
 
         let mut rng = StdRng::seed_from_u64(seed);
         let first_bad = rng.gen_range(0..commit_count - 1);
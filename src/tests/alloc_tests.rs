@@ -2,7 +2,10 @@
 use float_eq::assert_float_eq;
 
 use crate::{
-    alloc::{BasicAllocator, DumbAllocator},
+    alloc::{
+        BasicAllocator, DumbAllocator, KAryAllocator, ProbabilisticAllocator, RuntimeAllocator,
+        SpeculativeAllocator, WeightedAllocator,
+    },
     tests::alloc_bencher::run_bench,
 };
 
@@ -34,96 +37,96 @@ fn two_commits_one_runner_basic_bookends() {
 fn one_thousand_commits_eight_runners_dumb_bookends() {
     let res = run_bench::<DumbAllocator>(1000, 8, 100.0, 1.0, 100, true);
 
-    let expected_steps = 4756;
+    let expected_steps = 5410;
     assert_eq!(
         res.1, expected_steps,
         "Dumb allocator does not match expected # of steps ({expected_steps})"
     );
-    assert_float_eq!(res.0, 470921.06, r2nd <= 0.000_1);
+    assert_float_eq!(res.0, 113335.42, r2nd <= 0.000_1);
 }
 
 #[test]
 fn one_thousand_commits_eight_runners_basic_bookends() {
     let res = run_bench::<BasicAllocator>(1000, 8, 100.0, 1.0, 100, true);
 
-    let expected_steps = 1273;
+    let expected_steps = 1295;
     assert_eq!(
         res.1, expected_steps,
         "Basic allocator does not match expected # of steps ({expected_steps})"
     );
-    assert_float_eq!(res.0, 126346.02, r2nd <= 0.000_1);
+    assert_float_eq!(res.0, 37641.19, r2nd <= 0.000_1);
 }
 
 #[test]
 fn one_thousand_commits_eight_runners_dumb() {
     let res = run_bench::<DumbAllocator>(1000, 8, 100.0, 1.0, 100, false);
 
-    let expected_steps = 3640;
+    let expected_steps = 3482;
     assert_eq!(
         res.1, expected_steps,
         "Dumb allocator does not match expected # of steps ({expected_steps})"
     );
-    assert_float_eq!(res.0, 360359.05, r2nd <= 0.000_1);
+    assert_float_eq!(res.0, 80985.18, r2nd <= 0.000_1);
 }
 
 #[test]
 fn one_thousand_commits_eight_runners_basic() {
     let res = run_bench::<BasicAllocator>(1000, 8, 100.0, 1.0, 100, false);
 
-    let expected_steps = 1148;
+    let expected_steps = 1189;
     assert_eq!(
         res.1, expected_steps,
         "Basic allocator does not match expected # of steps ({expected_steps})"
     );
-    assert_float_eq!(res.0, 113924.75, r2nd <= 0.000_1);
+    assert_float_eq!(res.0, 35132.72, r2nd <= 0.000_1);
 }
 
 #[test]
 fn one_hundred_commits_eight_runners_dumb_bookends() {
     let res = run_bench::<DumbAllocator>(100, 8, 100.0, 1.0, 100, true);
 
-    let expected_steps = 1097;
+    let expected_steps = 914;
     assert_eq!(
         res.1, expected_steps,
         "Dumb allocator does not match expected # of steps ({expected_steps})"
     );
-    assert_float_eq!(res.0, 109046.75, r2nd <= 0.000_1);
+    assert_float_eq!(res.0, 24874.40, r2nd <= 0.000_1);
 }
 
 #[test]
 fn one_hundred_commits_eight_runners_basic_bookends() {
     let res = run_bench::<BasicAllocator>(100, 8, 100.0, 1.0, 100, true);
 
-    let expected_steps = 836;
+    let expected_steps = 867;
     assert_eq!(
         res.1, expected_steps,
         "Basic allocator does not match expected # of steps ({expected_steps})"
     );
-    assert_float_eq!(res.0, 83156.53, r2nd <= 0.000_1);
+    assert_float_eq!(res.0, 24686.44, r2nd <= 0.000_1);
 }
 
 #[test]
 fn one_hundred_commits_eight_runners_dumb() {
     let res = run_bench::<DumbAllocator>(100, 8, 100.0, 1.0, 100, false);
 
-    let expected_steps = 875;
+    let expected_steps = 823;
     assert_eq!(
         res.1, expected_steps,
         "Dumb allocator does not match expected # of steps ({expected_steps})"
     );
-    assert_float_eq!(res.0, 87109.42, r2nd <= 0.000_1);
+    assert_float_eq!(res.0, 22091.48, r2nd <= 0.000_1);
 }
 
 #[test]
 fn one_hundred_commits_eight_runners_basic() {
     let res = run_bench::<BasicAllocator>(100, 8, 100.0, 1.0, 100, false);
 
-    let expected_steps = 853;
+    let expected_steps = 843;
     assert_eq!(
         res.1, expected_steps,
         "Basic allocator does not match expected # of steps ({expected_steps})"
     );
-    assert_float_eq!(res.0, 84911.47, r2nd <= 0.000_1);
+    assert_float_eq!(res.0, 22581.33, r2nd <= 0.000_1);
 }
 
 #[test]
@@ -173,3 +176,244 @@ fn one_hundred_commits_one_runner_basic() {
     );
     assert_float_eq!(res.0, 67240.03, r2nd <= 0.000_1);
 }
+
+#[test]
+fn one_hundred_commits_eight_runners_weighted_bookends() {
+    let res = run_bench::<WeightedAllocator>(100, 8, 100.0, 1.0, 100, true);
+
+    let expected_steps = 795;
+    assert_eq!(
+        res.1, expected_steps,
+        "Weighted allocator does not match expected # of steps ({expected_steps})"
+    );
+    assert_float_eq!(res.0, 27114.23, r2nd <= 0.000_1);
+}
+
+#[test]
+fn one_hundred_commits_one_runner_weighted() {
+    let res = run_bench::<WeightedAllocator>(100, 1, 100.0, 1.0, 100, false);
+
+    let expected_steps = 675;
+    assert_eq!(
+        res.1, expected_steps,
+        "Weighted allocator does not match expected # of steps ({expected_steps})"
+    );
+    assert_float_eq!(res.0, 67674.70, r2nd <= 0.000_1);
+}
+
+#[test]
+fn one_thousand_commits_eight_runners_probabilistic_bookends() {
+    let res = run_bench::<ProbabilisticAllocator>(1000, 8, 100.0, 1.0, 100, true);
+
+    let expected_steps = 1169;
+    assert_eq!(
+        res.1, expected_steps,
+        "Probabilistic allocator does not match expected # of steps ({expected_steps})"
+    );
+    assert_float_eq!(res.0, 38345.28, r2nd <= 0.000_1);
+}
+
+#[test]
+fn one_hundred_commits_eight_runners_probabilistic_bookends() {
+    let res = run_bench::<ProbabilisticAllocator>(100, 8, 100.0, 1.0, 100, true);
+
+    let expected_steps = 774;
+    assert_eq!(
+        res.1, expected_steps,
+        "Probabilistic allocator does not match expected # of steps ({expected_steps})"
+    );
+    assert_float_eq!(res.0, 26241.54, r2nd <= 0.000_1);
+}
+
+#[test]
+fn one_hundred_commits_one_runner_probabilistic() {
+    let res = run_bench::<ProbabilisticAllocator>(100, 1, 100.0, 1.0, 100, false);
+
+    let expected_steps = 673;
+    assert_eq!(
+        res.1, expected_steps,
+        "Probabilistic allocator does not match expected # of steps ({expected_steps})"
+    );
+    assert_float_eq!(res.0, 67454.56, r2nd <= 0.000_1);
+}
+
+#[test]
+fn three_hundred_commits_eight_runners_varying_runtimes_runtime_bookends() {
+    let res = run_bench::<RuntimeAllocator>(300, 8, 100.0, 50.0, 100, true);
+
+    let expected_steps = 1031;
+    assert_eq!(
+        res.1, expected_steps,
+        "Runtime allocator does not match expected # of steps ({expected_steps})"
+    );
+    assert_float_eq!(res.0, 27920.18, r2nd <= 0.000_1);
+
+    let basic = run_bench::<BasicAllocator>(300, 8, 100.0, 50.0, 100, true);
+    assert!(
+        res.0 < basic.0,
+        "Runtime allocator took {} against basic {}",
+        res.0,
+        basic.0
+    );
+}
+
+#[test]
+fn one_hundred_commits_eight_runners_varying_runtimes_runtime_bookends() {
+    let res = run_bench::<RuntimeAllocator>(100, 8, 100.0, 50.0, 100, true);
+
+    let expected_steps = 827;
+    assert_eq!(
+        res.1, expected_steps,
+        "Runtime allocator does not match expected # of steps ({expected_steps})"
+    );
+    assert_float_eq!(res.0, 21406.45, r2nd <= 0.000_1);
+
+    let basic = run_bench::<BasicAllocator>(100, 8, 100.0, 50.0, 100, true);
+    assert!(
+        res.0 < basic.0,
+        "Runtime allocator took {} against basic {}",
+        res.0,
+        basic.0
+    );
+}
+
+#[test]
+fn three_hundred_commits_three_runners_varying_runtimes_runtime_bookends() {
+    let res = run_bench::<RuntimeAllocator>(300, 3, 100.0, 50.0, 100, true);
+
+    let expected_steps = 1003;
+    assert_eq!(
+        res.1, expected_steps,
+        "Runtime allocator does not match expected # of steps ({expected_steps})"
+    );
+    assert_float_eq!(res.0, 47325.53, r2nd <= 0.000_1);
+
+    let basic = run_bench::<BasicAllocator>(300, 3, 100.0, 50.0, 100, true);
+    assert!(
+        res.0 < basic.0,
+        "Runtime allocator took {} against basic {}",
+        res.0,
+        basic.0
+    );
+}
+
+#[test]
+fn one_thousand_commits_three_runners_varying_runtimes_speculative() {
+    let res = run_bench::<SpeculativeAllocator>(1000, 3, 100.0, 30.0, 100, false);
+
+    let expected_steps = 1096;
+    assert_eq!(
+        res.1, expected_steps,
+        "Speculative allocator does not match expected # of steps ({expected_steps})"
+    );
+    assert_float_eq!(res.0, 48283.52, r2nd <= 0.000_1);
+
+    // Runners finishing at different times leave slots free while others are in flight
+    let basic = run_bench::<BasicAllocator>(1000, 3, 100.0, 30.0, 100, false);
+    assert!(
+        res.0 < basic.0,
+        "Speculative allocator took {} against {}",
+        res.0,
+        basic.0
+    );
+}
+
+#[test]
+fn one_thousand_commits_eight_runners_k_ary_bookends() {
+    let res = run_bench::<KAryAllocator>(1000, 8, 100.0, 1.0, 100, true);
+
+    let expected_steps = 1260;
+    assert_eq!(
+        res.1, expected_steps,
+        "K-ary allocator does not match expected # of steps ({expected_steps})"
+    );
+    assert_float_eq!(res.0, 34654.68, r2nd <= 0.000_1);
+
+    let basic = run_bench::<BasicAllocator>(1000, 8, 100.0, 1.0, 100, true);
+    assert!(
+        res.0 < basic.0,
+        "K-ary allocator took {} against {}",
+        res.0,
+        basic.0
+    );
+}
+
+#[test]
+fn one_thousand_commits_eight_runners_k_ary() {
+    let res = run_bench::<KAryAllocator>(1000, 8, 100.0, 1.0, 100, false);
+
+    let expected_steps = 1199;
+    assert_eq!(
+        res.1, expected_steps,
+        "K-ary allocator does not match expected # of steps ({expected_steps})"
+    );
+    assert_float_eq!(res.0, 32926.31, r2nd <= 0.000_1);
+
+    let basic = run_bench::<BasicAllocator>(1000, 8, 100.0, 1.0, 100, false);
+    assert!(
+        res.0 < basic.0,
+        "K-ary allocator took {} against {}",
+        res.0,
+        basic.0
+    );
+}
+
+#[test]
+fn one_hundred_commits_eight_runners_k_ary_bookends() {
+    let res = run_bench::<KAryAllocator>(100, 8, 100.0, 1.0, 100, true);
+
+    let expected_steps = 773;
+    assert_eq!(
+        res.1, expected_steps,
+        "K-ary allocator does not match expected # of steps ({expected_steps})"
+    );
+    assert_float_eq!(res.0, 23674.47, r2nd <= 0.000_1);
+
+    let basic = run_bench::<BasicAllocator>(100, 8, 100.0, 1.0, 100, true);
+    assert!(
+        res.0 < basic.0,
+        "K-ary allocator took {} against {}",
+        res.0,
+        basic.0
+    );
+}
+
+#[test]
+fn one_hundred_commits_eight_runners_k_ary() {
+    let res = run_bench::<KAryAllocator>(100, 8, 100.0, 1.0, 100, false);
+
+    let expected_steps = 786;
+    assert_eq!(
+        res.1, expected_steps,
+        "K-ary allocator does not match expected # of steps ({expected_steps})"
+    );
+    assert_float_eq!(res.0, 22180.69, r2nd <= 0.000_1);
+
+    let basic = run_bench::<BasicAllocator>(100, 8, 100.0, 1.0, 100, false);
+    assert!(
+        res.0 < basic.0,
+        "K-ary allocator took {} against {}",
+        res.0,
+        basic.0
+    );
+}
+
+#[test]
+fn three_hundred_commits_eight_runners_varying_runtimes_k_ary_bookends() {
+    let res = run_bench::<KAryAllocator>(300, 8, 100.0, 50.0, 100, true);
+
+    let expected_steps = 1080;
+    assert_eq!(
+        res.1, expected_steps,
+        "K-ary allocator does not match expected # of steps ({expected_steps})"
+    );
+    assert_float_eq!(res.0, 28887.19, r2nd <= 0.000_1);
+
+    let basic = run_bench::<BasicAllocator>(300, 8, 100.0, 50.0, 100, true);
+    assert!(
+        res.0 < basic.0,
+        "K-ary allocator took {} against {}",
+        res.0,
+        basic.0
+    );
+}
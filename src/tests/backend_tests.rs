@@ -0,0 +1,215 @@
+use std::{env, fs, process};
+
+use crate::{
+    backend::{CliBackend, GitBackend},
+    tests::git,
+    Status,
+};
+
+/// Six commits each changing their own file, bisecting between the first and the last.
+fn bisecting_repo(name: &str) -> std::path::PathBuf {
+    let repo = env::temp_dir().join(format!("biasect-{name}-{}", process::id()));
+    fs::create_dir_all(&repo).unwrap();
+    git(&repo, &["init", "-q"]);
+    for i in 0..6 {
+        fs::write(repo.join(format!("file{i}.txt")), "content").unwrap();
+        git(&repo, &["add", "."]);
+        git(&repo, &["commit", "-q", "-m", &format!("Commit {i}")]);
+    }
+    git(&repo, &["bisect", "start", "HEAD", "HEAD~5"]);
+    repo
+}
+
+#[test]
+fn cli_backend() {
+    let repo = bisecting_repo("cli-backend");
+    let backend = CliBackend::new(&repo);
+
+    let commits = backend.commits().unwrap();
+    assert_eq!(commits.len(), 6);
+
+    let range_parents = backend.range_parents().unwrap();
+    assert_eq!(range_parents.len(), 5);
+    assert_eq!(range_parents[&commits[3]], vec![commits[2].clone()]);
+
+    let meta = backend.commit_meta(&commits[3]).unwrap();
+    assert_eq!(meta.files, vec!["file3.txt".to_string()]);
+    assert_eq!(meta.subject.as_deref(), Some("Commit 3"));
+
+    // One `git log` for the whole range, except the good commit it starts after
+    let metas = backend.range_meta().unwrap();
+    assert_eq!(metas.len(), 5);
+    for hash in &commits[1..] {
+        assert_eq!(metas[hash].files, backend.commit_meta(hash).unwrap().files);
+    }
+
+    backend.bisect_report(&Status::Good, &commits[2]).unwrap();
+    assert_eq!(
+        backend.bisect_log().unwrap().last().map(|x| x.0),
+        Some(Status::Good)
+    );
+
+    fs::remove_dir_all(repo).unwrap();
+}
+
+#[test]
+fn full_hashes_despite_log_config() {
+    let repo = bisecting_repo("log-config");
+    for (key, value) in [
+        ("log.decorate", "full"),
+        ("color.ui", "always"),
+        ("format.pretty", "oneline"),
+        ("log.abbrevCommit", "true"),
+    ] {
+        git(&repo, &["config", key, value]);
+    }
+    let backend = CliBackend::new(&repo);
+
+    let commits = backend.commits().unwrap();
+    assert_eq!(commits.len(), 6);
+    assert!(
+        commits
+            .iter()
+            .all(|x| x.len() == 40 && x.chars().all(|c| c.is_ascii_hexdigit())),
+        "Not full hashes: {commits:?}"
+    );
+    assert_eq!(
+        git(&repo, &["rev-parse", "refs/bisect/bad"]).trim(),
+        commits[5]
+    );
+
+    let metas = backend.range_meta().unwrap();
+    assert!(commits[1..].iter().all(|x| metas.contains_key(x)));
+
+    fs::remove_dir_all(repo).unwrap();
+}
+
+#[test]
+fn range_meta_of_unusual_file_names() {
+    let repo = bisecting_repo("file-names");
+    git(&repo, &["bisect", "reset"]);
+    git(&repo, &["config", "core.quotePath", "true"]);
+    git(&repo, &["config", "log.showSignature", "true"]);
+
+    let names = [
+        "fïlé.txt",
+        "two\nlines.txt",
+        "\nleading newline.txt",
+        "tab\t\"quoted\".txt",
+    ];
+    for name in names {
+        fs::write(repo.join(name), "content").unwrap();
+    }
+    git(&repo, &["add", "."]);
+    git(&repo, &["commit", "-q", "-m", "Unusual names"]);
+
+    // A merge, which lists no files
+    git(&repo, &["checkout", "-q", "-b", "side", "HEAD~1"]);
+    git(&repo, &["commit", "-q", "--allow-empty", "-m", "Side"]);
+    git(&repo, &["checkout", "-q", "-"]);
+    git(&repo, &["merge", "-q", "--no-edit", "side"]);
+    git(&repo, &["bisect", "start", "HEAD", "HEAD~3"]);
+
+    let backend = CliBackend::new(&repo);
+    let metas = backend.range_meta().unwrap();
+    let unusual = git(&repo, &["rev-parse", "refs/bisect/bad^1"]);
+    let mut files = metas[unusual.trim()].files.clone();
+    files.sort();
+    let mut expected = names.map(|x| x.to_string()).to_vec();
+    expected.sort();
+    assert_eq!(files, expected);
+    assert_eq!(
+        metas[unusual.trim()].files,
+        backend.commit_meta(unusual.trim()).unwrap().files
+    );
+
+    let merge = git(&repo, &["rev-parse", "refs/bisect/bad"]);
+    assert!(metas[merge.trim()].files.is_empty());
+
+    fs::remove_dir_all(repo).unwrap();
+}
+
+#[cfg(feature = "libgit2")]
+#[test]
+fn libgit2_backend_matches_cli() {
+    use crate::{backend::Libgit2Backend, shell::ShellError};
+
+    let repo = bisecting_repo("libgit2-backend");
+    let cli = CliBackend::new(&repo);
+    let lib = Libgit2Backend::open(&repo).unwrap();
+
+    let lib_commits = lib.commits().unwrap();
+    assert_eq!(cli.commits().unwrap(), lib_commits);
+
+    assert_eq!(
+        lib.parents(&lib_commits[3]).unwrap(),
+        vec![lib_commits[2].clone()]
+    );
+    for hash in &lib_commits {
+        let (cli_meta, lib_meta) = (
+            cli.commit_meta(hash).unwrap(),
+            lib.commit_meta(hash).unwrap(),
+        );
+        assert_eq!(cli_meta.files, lib_meta.files);
+        assert_eq!(cli_meta.author, lib_meta.author);
+        assert_eq!(cli_meta.subject, lib_meta.subject);
+    }
+
+    // Marks made in-process are seen by git
+    lib.bisect_report(&Status::Good, &lib_commits[2]).unwrap();
+    lib.bisect_report(&Status::Skip, &lib_commits[3]).unwrap();
+    assert_eq!(cli.bisect_log().unwrap(), lib.bisect_log().unwrap());
+    assert_eq!(
+        &lib.bisect_log().unwrap()[2..],
+        &[
+            (Status::Good, lib_commits[2].clone()),
+            (Status::Skip, lib_commits[3].clone())
+        ]
+    );
+    assert_eq!(cli.commits().unwrap().len(), 4);
+
+    git(&repo, &["bisect", "reset"]);
+    assert!(matches!(lib.commits(), Err(ShellError::NoBisect)));
+
+    fs::remove_dir_all(repo).unwrap();
+}
+
+#[cfg(feature = "libgit2")]
+#[test]
+fn libgit2_backend_logs_first_bad_commit() {
+    use std::path::Path;
+
+    use crate::backend::Libgit2Backend;
+
+    let cli_repo = bisecting_repo("first-bad-cli");
+    let lib_repo = bisecting_repo("first-bad-libgit2");
+    let cli = CliBackend::new(&cli_repo);
+    let lib = Libgit2Backend::open(&lib_repo).unwrap();
+    let commits = cli.commits().unwrap();
+
+    // Commits differ by their timestamps, so mark the same positions in both
+    for (backend, commits) in [
+        (&cli as &dyn GitBackend, commits),
+        (&lib, lib.commits().unwrap()),
+    ] {
+        backend.bisect_report(&Status::Good, &commits[3]).unwrap();
+        backend.bisect_report(&Status::Bad, &commits[4]).unwrap();
+    }
+
+    let last_line = |repo: &Path| {
+        let log = fs::read_to_string(repo.join(".git/BISECT_LOG")).unwrap();
+        let line = log.lines().last().unwrap().to_string();
+        line.replace(
+            git(repo, &["rev-parse", "refs/bisect/bad"]).trim(),
+            "<hash>",
+        )
+    };
+    assert_eq!(
+        last_line(&lib_repo),
+        "# first bad commit: [<hash>] Commit 4"
+    );
+    assert_eq!(last_line(&cli_repo), last_line(&lib_repo));
+
+    fs::remove_dir_all(cli_repo).unwrap();
+    fs::remove_dir_all(lib_repo).unwrap();
+}
@@ -0,0 +1,176 @@
+use float_eq::assert_float_eq;
+
+use crate::{
+    alloc::{init, Allocator, BasicAllocator, WeightedAllocator},
+    bias::{path_weight, weigh_commits, BiasConfig, BiasRule, IGNORE_MULTIPLIER},
+    CommitMeta, CommitState, Status,
+};
+
+fn rules(rules: &[&str]) -> Vec<BiasRule> {
+    rules.iter().map(|r| r.parse().unwrap()).collect()
+}
+
+fn files(files: &[&str]) -> Vec<String> {
+    files.iter().map(|f| f.to_string()).collect()
+}
+
+#[test]
+fn rule_parsing() {
+    let rule: BiasRule = "src/**/*.c=2.5".parse().unwrap();
+    assert_eq!(rule.pattern.as_str(), "src/**/*.c");
+    assert_float_eq!(rule.multiplier, 2.5, abs <= 0.0);
+
+    assert!("*.c".parse::<BiasRule>().is_err());
+    assert!("*.c=two".parse::<BiasRule>().is_err());
+    assert!("*.c=-1".parse::<BiasRule>().is_err());
+}
+
+#[test]
+fn path_weight_uses_largest_multiplier() {
+    let rules = rules(&["*.c=2", "docs/**=0"]);
+
+    assert_float_eq!(
+        path_weight(&files(&["src/main.c"]), &rules),
+        2.0,
+        abs <= 0.0
+    );
+    assert_float_eq!(path_weight(&files(&["README.md"]), &rules), 1.0, abs <= 0.0);
+    assert_float_eq!(
+        path_weight(&files(&["docs/index.md"]), &rules),
+        0.0,
+        abs <= 0.0
+    );
+    assert_float_eq!(
+        path_weight(&files(&["docs/index.md", "src/main.c"]), &rules),
+        2.0,
+        abs <= 0.0
+    );
+    assert_float_eq!(path_weight(&[], &rules), 1.0, abs <= 0.0);
+}
+
+#[test]
+fn first_matching_rule_wins() {
+    let rules = rules(&["docs/*.c=0", "*.c=2"]);
+
+    assert_float_eq!(
+        path_weight(&files(&["docs/example.c"]), &rules),
+        0.0,
+        abs <= 0.0
+    );
+}
+
+fn commits_touching(files_per_commit: &[&[&str]]) -> Vec<CommitState> {
+    files_per_commit
+        .iter()
+        .enumerate()
+        .map(|(i, f)| {
+            CommitState::new(
+                i.to_string(),
+                CommitMeta {
+                    files: files(f),
+                    ..Default::default()
+                },
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn commit_weights_are_normalized() {
+    let config = BiasConfig {
+        paths: rules(&["*.c=2", "docs/**=0"]),
+        ..Default::default()
+    };
+    let mut commits = commits_touching(&[&["a.c"], &["docs/a.md"], &["a.h"], &["b.h"]]);
+    weigh_commits(&mut commits, &config);
+
+    let state = init::<BasicAllocator>(commits, 1, false);
+    let weights = state.commits.iter().map(|c| c.weight).collect::<Vec<_>>();
+
+    assert_float_eq!(weights, vec![0.5, 0.0, 0.25, 0.25], abs_all <= 1e-12);
+}
+
+#[test]
+fn all_zero_weights_fall_back_to_uniform() {
+    let config = BiasConfig {
+        paths: rules(&["docs/**=0"]),
+        ..Default::default()
+    };
+    let mut commits = commits_touching(&[&["docs/a.md"], &["docs/b.md"]]);
+    weigh_commits(&mut commits, &config);
+
+    let state = init::<BasicAllocator>(commits, 1, false);
+    let weights = state.commits.iter().map(|c| c.weight).collect::<Vec<_>>();
+
+    assert_float_eq!(weights, vec![0.5, 0.5], abs_all <= 1e-12);
+}
+
+#[test]
+fn weighted_allocator_follows_mass() {
+    // All of the mass is at the end of the range, so the median is there too.
+    let commits = (0..10)
+        .map(|i| CommitState {
+            hash: i.to_string(),
+            status: Status::Unknown,
+            weight: if i >= 8 { 0.5 } else { 0.0 },
+            meta: Default::default(),
+            parents: Default::default(),
+        })
+        .collect::<Vec<_>>();
+
+    let allocated = WeightedAllocator::alloc_runners(1, &[], (0, &commits), false);
+
+    assert_eq!(allocated, vec![8]);
+}
+
+#[test]
+fn config_parsing() {
+    let config = BiasConfig::parse(
+        r#"
+        ignore = ["docs/**"]
+
+        [[path]]
+        pattern = "*.c"
+        multiplier = 2
+
+        [[author]]
+        pattern = "\\[bot\\]"
+        multiplier = 0.1
+
+        [[message]]
+        pattern = "^Revert"
+        multiplier = 3
+        "#,
+    )
+    .unwrap();
+
+    let meta = |files: &[&str], author: &str, subject: &str| CommitMeta {
+        files: files.iter().map(|f| f.to_string()).collect(),
+        author: Some(author.to_string()),
+        subject: Some(subject.to_string()),
+    };
+
+    assert_float_eq!(
+        config.commit_weight(&meta(&["docs/a.c"], "Jo <jo@example.com>", "Fix docs")),
+        IGNORE_MULTIPLIER,
+        rmax <= 1e-12
+    );
+    assert_float_eq!(
+        config.commit_weight(&meta(&["a.c"], "ci[bot] <ci@example.com>", "Bump")),
+        0.2,
+        rmax <= 1e-12
+    );
+    assert_float_eq!(
+        config.commit_weight(&meta(&["a.h"], "Jo <jo@example.com>", "Revert \"a\"")),
+        3.0,
+        rmax <= 1e-12
+    );
+}
+
+#[test]
+fn invalid_config() {
+    assert!(BiasConfig::parse("[[path]]\npattern = \"*.c\"").is_err());
+    assert!(BiasConfig::parse("[[path]]\npattern = \"*.c\"\nmultiplier = -1").is_err());
+    assert!(BiasConfig::parse("[[author]]\npattern = \"(\"\nmultiplier = 1").is_err());
+    assert!(BiasConfig::parse("unknown = 1").is_err());
+}
@@ -0,0 +1,173 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    alloc::{
+        init, is_linear, step, suspects, Allocator, BasicAllocator, DumbAllocator,
+        ProbabilisticAllocator, WeightedAllocator,
+    },
+    CommitMeta, CommitState, Runners, State, Status,
+};
+
+fn dag(parents: &[&[usize]]) -> Vec<CommitState> {
+    parents
+        .iter()
+        .enumerate()
+        .map(|(i, parents)| CommitState {
+            parents: parents.to_vec().into(),
+            ..CommitState::new(i.to_string(), CommitMeta::default())
+        })
+        .collect()
+}
+
+/// 0 - 1 - 2 - 4 - 5
+///      \     /
+///       - 3 -
+fn merge() -> Vec<CommitState> {
+    let mut commits = dag(&[&[], &[0], &[1], &[1], &[2, 3], &[4]]);
+    commits[0].status = Status::Good;
+    commits[5].status = Status::Bad;
+    commits
+}
+
+fn state(commits: Vec<CommitState>, runners: Vec<usize>) -> State {
+    State {
+        runtime_samples: vec![],
+        runners: Runners {
+            start_times: runners.iter().map(|_| 0.0).collect(),
+            total: runners.len(),
+            commits: runners,
+        },
+        commits,
+        check_bookends: false,
+        flaky: None,
+    }
+}
+
+#[test]
+fn linear_histories() {
+    assert!(is_linear(&dag(&[&[], &[], &[]])));
+    assert!(is_linear(&dag(&[&[], &[0], &[1]])));
+    assert!(!is_linear(&merge()));
+}
+
+#[test]
+fn good_clears_only_ancestors() {
+    let mut commits = merge();
+    assert_eq!(suspects(&commits), vec![1, 2, 3, 4, 5]);
+
+    // 2 comes before 3, but isn't its ancestor
+    commits[3].status = Status::Good;
+    assert_eq!(suspects(&commits), vec![2, 4, 5]);
+}
+
+#[test]
+fn bad_clears_everything_but_ancestors() {
+    let mut commits = merge();
+
+    commits[4].status = Status::Bad;
+    assert_eq!(suspects(&commits), vec![1, 2, 3, 4]);
+
+    // 3 isn't an ancestor of 2, and can't be the first bad commit anymore
+    commits[2].status = Status::Bad;
+    assert_eq!(suspects(&commits), vec![1, 2]);
+}
+
+#[test]
+fn step_invalidates_along_dag() {
+    let (stepped, invalidated, _) =
+        step::<BasicAllocator>(&state(merge(), vec![1, 2, 3]), Status::Good, 3, 1.0, 1.0);
+    assert_eq!(invalidated, [1, 3].into());
+    assert!(stepped.runners.commits.contains(&2));
+
+    let (stepped, invalidated, _) =
+        step::<BasicAllocator>(&state(merge(), vec![1, 2, 3]), Status::Bad, 2, 1.0, 1.0);
+    assert_eq!(invalidated, [2, 3].into());
+    assert_eq!(stepped.runners.commits, vec![1]);
+}
+
+/// A range of `count` commits with branches and merges, every one of them an ancestor of the last.
+fn random_dag(rng: &mut StdRng, count: usize) -> Vec<CommitState> {
+    let mut parents: Vec<Vec<usize>> = vec![vec![]];
+    for i in 1..count {
+        let mut commit_parents = vec![rng.gen_range(i.saturating_sub(4)..i)];
+        if rng.gen_bool(0.2) {
+            let merged = rng.gen_range(0..i);
+            if !commit_parents.contains(&merged) {
+                commit_parents.push(merged);
+            }
+        }
+        parents.push(commit_parents);
+    }
+
+    // Merge the heads of every branch into the last commit
+    let heads = (0..count - 1)
+        .filter(|x| !parents.iter().any(|p| p.contains(x)))
+        .collect::<Vec<_>>();
+    parents[count - 1].extend(heads);
+
+    let mut commits = dag(&parents.iter().map(|x| x.as_slice()).collect::<Vec<_>>());
+    commits[0].status = Status::Good;
+    commits[count - 1].status = Status::Bad;
+    commits
+}
+
+fn is_ancestor(commits: &[CommitState], ancestor: usize, commit: usize) -> bool {
+    ancestor == commit
+        || commits[commit]
+            .parents
+            .iter()
+            .any(|x| is_ancestor(commits, ancestor, *x))
+}
+
+/// Bisect random histories where everything that has `first_bad` as an ancestor is bad.
+/// Returns the total number of steps.
+fn bisect_random_dags<F>(runners: usize) -> usize
+where
+    F: Allocator,
+{
+    let mut steps = 0;
+    for seed in 0..20 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let commits = random_dag(&mut rng, 40);
+        let first_bad = rng.gen_range(1..commits.len());
+        let truths = (0..commits.len())
+            .map(|x| {
+                if is_ancestor(&commits, first_bad, x) {
+                    Status::Bad
+                } else {
+                    Status::Good
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut state = init::<F>(commits, runners, false);
+        while let Some(commit) = state.runners.commits.first().copied() {
+            steps += 1;
+            (state, _, _) = step::<F>(&state, truths[commit], commit, 1.0, 1.0);
+        }
+
+        assert_eq!(
+            suspects(&state.commits),
+            vec![first_bad],
+            "Seed {seed} didn't find the first bad commit"
+        );
+    }
+    steps
+}
+
+#[test]
+fn finds_first_bad_commit_in_random_dags() {
+    bisect_random_dags::<DumbAllocator>(1);
+    bisect_random_dags::<DumbAllocator>(3);
+    bisect_random_dags::<WeightedAllocator>(3);
+
+    // Halving the suspects takes about log2(40) steps per history, like without merges
+    let basic = bisect_random_dags::<BasicAllocator>(1);
+    assert!(basic <= 20 * 8, "Basic allocator took {basic} steps");
+    let probabilistic = bisect_random_dags::<ProbabilisticAllocator>(1);
+    assert!(
+        probabilistic <= 20 * 8,
+        "Probabilistic allocator took {probabilistic} steps"
+    );
+    bisect_random_dags::<ProbabilisticAllocator>(4);
+}
@@ -0,0 +1,90 @@
+use std::{env, fs, os::unix::fs::PermissionsExt, path::Path, process, time::Duration};
+
+use crate::{
+    exit::{CodeSet, Exit, ExitCodeMap, Outcome},
+    shell::run_script,
+    Status,
+};
+
+#[test]
+fn git_bisect_run_semantics() {
+    let map = ExitCodeMap::default();
+
+    assert_eq!(map.outcome(Exit::Code(0)), Outcome::Mark(Status::Good));
+    assert_eq!(map.outcome(Exit::Code(1)), Outcome::Mark(Status::Bad));
+    assert_eq!(map.outcome(Exit::Code(124)), Outcome::Mark(Status::Bad));
+    assert_eq!(map.outcome(Exit::Code(125)), Outcome::Mark(Status::Skip));
+    assert_eq!(map.outcome(Exit::Code(127)), Outcome::Mark(Status::Bad));
+    assert_eq!(map.outcome(Exit::Code(128)), Outcome::Abort);
+    assert_eq!(map.outcome(Exit::Code(255)), Outcome::Abort);
+    assert_eq!(map.outcome(Exit::Code(-1)), Outcome::Abort);
+    assert_eq!(map.outcome(Exit::Signal(9)), Outcome::Abort);
+    assert_eq!(
+        map.outcome(Exit::Timeout(Duration::from_secs(300))),
+        Outcome::Mark(Status::Skip)
+    );
+}
+
+#[test]
+fn custom_codes() {
+    let map = ExitCodeMap {
+        skip: "124,125".parse().unwrap(),
+        abort: "255".parse().unwrap(),
+        signal: "skip".parse().unwrap(),
+        timeout: "bad".parse().unwrap(),
+    };
+
+    assert_eq!(map.outcome(Exit::Code(124)), Outcome::Mark(Status::Skip));
+    assert_eq!(map.outcome(Exit::Code(125)), Outcome::Mark(Status::Skip));
+    assert_eq!(map.outcome(Exit::Code(139)), Outcome::Mark(Status::Bad));
+    assert_eq!(map.outcome(Exit::Code(255)), Outcome::Abort);
+    // A signal is not the same as its exit code
+    assert_eq!(map.outcome(Exit::Signal(127)), Outcome::Mark(Status::Skip));
+    assert_eq!(
+        map.outcome(Exit::Timeout(Duration::from_secs(1))),
+        Outcome::Mark(Status::Bad)
+    );
+}
+
+#[test]
+fn code_set_parsing() {
+    let codes: CodeSet = "1, 124-126,200".parse().unwrap();
+
+    assert!(codes.contains(1));
+    assert!(!codes.contains(2));
+    assert!(codes.contains(124));
+    assert!(codes.contains(126));
+    assert!(codes.contains(200));
+
+    assert!("1,a".parse::<CodeSet>().is_err());
+    assert!("1-".parse::<CodeSet>().is_err());
+}
+
+/// How `command` exits when run like a runner, in `dir`.
+fn run_exit(command: &str, dir: &Path) -> Exit {
+    let mut child = run_script("true", command, dir, &dir.join("log")).unwrap();
+    child.wait().unwrap().into()
+}
+
+#[test]
+fn scripts_killed_by_a_signal() {
+    let dir = env::temp_dir().join(format!("biasect-signal-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let script = dir.join("crash.sh");
+    fs::write(&script, "#!/bin/sh\nkill -TERM $$\n").unwrap();
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+
+    assert_eq!(run_exit("./crash.sh", &dir), Exit::Signal(libc::SIGTERM));
+    assert_eq!(run_exit("sh crash.sh", &dir), Exit::Signal(libc::SIGTERM));
+    // A shell in between can only pass on the exit code it made of the signal
+    assert_eq!(
+        run_exit("true && ./crash.sh", &dir),
+        Exit::Code(128 + libc::SIGTERM)
+    );
+
+    // Builtins aren't programs to replace the shell with
+    assert_eq!(run_exit("exit 3", &dir), Exit::Code(3));
+    assert_eq!(run_exit("test -d .", &dir), Exit::Code(0));
+
+    fs::remove_dir_all(dir).unwrap();
+}
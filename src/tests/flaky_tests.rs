@@ -0,0 +1,108 @@
+use float_eq::assert_float_eq;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    alloc::{init_flaky, step, BasicAllocator},
+    flaky::{condition_on_known, most_likely, Flaky},
+    tests::commits,
+    Status,
+};
+
+#[test]
+fn rejects_invalid_probabilities() {
+    assert!(Flaky::new(0.99, 0.05).is_ok());
+    assert!(Flaky::new(1.0, 0.05).is_err());
+    assert!(Flaky::new(0.0, 0.05).is_err());
+    assert!(Flaky::new(0.99, 0.5).is_err());
+    assert!(Flaky::new(0.99, 0.0).is_err());
+}
+
+#[test]
+fn known_commits_condition_prior() {
+    let mut commits = commits(5);
+    commits[2].status = Status::Good;
+    condition_on_known(&mut commits);
+
+    // Only 3 and 4 can still be the first bad commit
+    let weights = commits.iter().map(|x| x.weight).collect::<Vec<_>>();
+    assert_eq!(weights, vec![0.0, 0.0, 0.0, 0.5, 0.5]);
+}
+
+#[test]
+fn results_update_weights() {
+    let flaky = Flaky::new(0.99, 0.1).unwrap();
+    let mut commits = commits(5);
+    condition_on_known(&mut commits);
+
+    // 1 and 2 explain a bad result, 3 and 4 only a wrong one
+    flaky.observe(&mut commits, 2, Status::Bad);
+    assert_float_eq!(commits[1].weight, 0.45, abs <= 1e-9);
+    assert_float_eq!(commits[2].weight, 0.45, abs <= 1e-9);
+    assert_float_eq!(commits[3].weight, 0.05, abs <= 1e-9);
+    assert_float_eq!(commits[4].weight, 0.05, abs <= 1e-9);
+
+    // A contradicting result undoes it
+    flaky.observe(&mut commits, 2, Status::Good);
+    assert_float_eq!(commits[1].weight, 0.25, abs <= 1e-9);
+    assert_float_eq!(commits[4].weight, 0.25, abs <= 1e-9);
+
+    // Skips tell nothing
+    flaky.observe(&mut commits, 2, Status::Skip);
+    assert_float_eq!(commits[1].weight, 0.25, abs <= 1e-9);
+    assert_eq!(flaky.decided(&commits), None);
+}
+
+/// Bisect 100 commits with a script that gives the wrong result 10% of the time.
+/// Returns the actual first bad commit, the one found, and how many times commits were tested again.
+fn bisect_flaky(runners: usize, seed: u64) -> (usize, usize, usize) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let first_bad = rng.gen_range(1..100);
+    let flaky = Flaky::new(0.99, 0.1).unwrap();
+
+    let mut state = init_flaky(commits(100), runners, flaky);
+    let mut tested = vec![0usize; 100];
+    let mut time = 0.0;
+    while !state.runners.commits.is_empty() {
+        // Runners finish in a random order
+        let commit = state.runners.commits[rng.gen_range(0..state.runners.commits.len())];
+        tested[commit] += 1;
+        let correct = commit >= first_bad;
+        let bad = if rng.gen_bool(0.1) { !correct } else { correct };
+        let status = if bad { Status::Bad } else { Status::Good };
+
+        time += 1.0;
+        (state, _, _) = step::<BasicAllocator>(&state, status, commit, 1.0, time);
+        assert!(time < 1000.0, "Seed {seed} didn't finish");
+    }
+
+    let found = state
+        .commits
+        .iter()
+        .position(|x| x.status == Status::Bad)
+        .unwrap();
+    assert_eq!(state.commits[found - 1].status, Status::Good);
+    assert!(most_likely(&state.commits).unwrap().1 >= 0.99);
+
+    let retests = tested.iter().map(|x| x.saturating_sub(1)).sum();
+    (first_bad, found, retests)
+}
+
+#[test]
+fn finds_first_bad_commit_despite_flakes() {
+    for runners in [1, 4] {
+        let mut correct = 0;
+        let mut retests = 0;
+        for seed in 0..20 {
+            let (first_bad, found, seed_retests) = bisect_flaky(runners, seed);
+            correct += usize::from(first_bad == found);
+            retests += seed_retests;
+        }
+
+        // 99% confidence allows for the odd miss, but not more
+        assert!(
+            correct >= 19,
+            "{runners} runners found {correct} of 20 first bad commits"
+        );
+        assert!(retests > 0, "{runners} runners never tested a commit again");
+    }
+}
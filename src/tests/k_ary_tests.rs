@@ -0,0 +1,78 @@
+use crate::{
+    alloc::{Allocator, BasicAllocator, KAryAllocator, Timing},
+    tests::{commits, sorted},
+    Status,
+};
+
+#[test]
+fn bisects_with_one_runner() {
+    let commits = commits(17);
+
+    let allocated = KAryAllocator::alloc_runners(1, &[], (1, &commits[1..16]), false);
+    assert_eq!(allocated, vec![8]);
+}
+
+#[test]
+fn finishes_most_parts_in_a_round() {
+    // Six of the parts take a single round more, where equal intervals would leave all of them two
+    let allocated = KAryAllocator::alloc_initial(&commits(100), 8, false);
+    assert_eq!(sorted(allocated), vec![9, 18, 27, 36, 45, 54, 65, 82]);
+}
+
+#[test]
+fn leaves_untested_commits_together() {
+    let commits = commits(12);
+
+    // 1 and 2 together leave 3 commits to another round, where 1 and 10 would leave 4
+    let allocated = KAryAllocator::alloc_runners(8, &[], (1, &commits[1..11]), false);
+    assert_eq!(sorted(allocated), vec![3, 4, 5, 6, 7, 8, 9, 10]);
+
+    let allocated = BasicAllocator::alloc_runners(8, &[], (1, &commits[1..11]), false);
+    assert_eq!(sorted(allocated), vec![2, 3, 4, 5, 6, 7, 8, 9]);
+}
+
+#[test]
+fn spends_runners_where_they_save_the_most_rounds() {
+    let commits = commits(17);
+
+    // What's left below the runner in flight takes a single round either way
+    let allocated = KAryAllocator::alloc_runners(3, &[3], (1, &commits[1..16]), false);
+    assert_eq!(sorted(allocated), vec![8, 12]);
+
+    let allocated = KAryAllocator::alloc_runners(3, &[8], (1, &commits[1..16]), false);
+    assert_eq!(sorted(allocated), vec![4, 12]);
+}
+
+#[test]
+fn skips_known_commits() {
+    let mut commits = commits(17);
+    commits[8].status = Status::Skip;
+
+    // Nor the commits next to it, which are likely to be skipped too
+    let allocated = KAryAllocator::alloc_runners(1, &[], (1, &commits[1..16]), false);
+    assert_eq!(allocated, vec![6]);
+}
+
+#[test]
+fn spends_runners_where_waiting_doesnt_hide_their_rounds() {
+    let commits = commits(33);
+    let samples = [10.0, 10.0, 10.0, 100.0];
+
+    // The runner on 8 has run longer than 10, so it's expected to take 100 and hold up the commits up to 24 for a
+    // while, whatever runs there meanwhile. The one on 24 is about done, so a runner above it is worth more.
+    let allocated = KAryAllocator::alloc_timed(
+        4,
+        &[8, 24],
+        (1, &commits[1..32]),
+        false,
+        &Timing {
+            runtime_samples: &samples,
+            start_times: &[90.0, 0.0],
+            now: 100.0,
+        },
+    );
+    assert_eq!(sorted(allocated), vec![17, 29]);
+
+    let allocated = KAryAllocator::alloc_runners(4, &[8, 24], (1, &commits[1..32]), false);
+    assert_eq!(sorted(allocated), vec![14, 19]);
+}
@@ -0,0 +1,25 @@
+use std::{env, fs, process};
+
+use crate::persist::{log_path, logs_dir, reset_logs, tail_log};
+
+#[test]
+fn logs_are_reset_and_tailed() {
+    let dir = env::temp_dir().join(format!("biasect-log-test-{}", process::id()));
+    let logs = logs_dir(&dir);
+
+    reset_logs(&logs).unwrap();
+    let path = log_path(&logs, "c0ffee");
+    fs::write(&path, "one\ntwo\nthree\n").unwrap();
+
+    assert_eq!(tail_log(&path, 2).unwrap(), "two\nthree");
+    assert_eq!(tail_log(&path, 10).unwrap(), "one\ntwo\nthree");
+    assert_eq!(tail_log(&path, 0).unwrap(), "");
+
+    // A new bisection doesn't see the logs of the last one
+    reset_logs(&logs).unwrap();
+    assert!(!path.exists());
+    assert!(logs.is_dir());
+    assert!(tail_log(&path, 2).is_err());
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -1,3 +1,82 @@
+#[cfg(test)]
+use std::{path::Path, process::Command};
+
+#[cfg(test)]
+use crate::{CommitMeta, CommitState, Status};
+
 pub mod alloc_bencher;
 #[cfg(test)]
 pub mod alloc_tests;
+#[cfg(test)]
+pub mod backend_tests;
+#[cfg(test)]
+pub mod bias_tests;
+#[cfg(test)]
+pub mod dag_tests;
+#[cfg(test)]
+pub mod exit_tests;
+#[cfg(test)]
+pub mod flaky_tests;
+#[cfg(test)]
+pub mod k_ary_tests;
+#[cfg(test)]
+pub mod log_tests;
+#[cfg(test)]
+pub mod probabilistic_tests;
+#[cfg(test)]
+pub mod reaper_tests;
+#[cfg(test)]
+pub mod resume_tests;
+#[cfg(test)]
+pub mod runtime_tests;
+#[cfg(test)]
+pub mod shell_tests;
+#[cfg(test)]
+pub mod skip_tests;
+#[cfg(test)]
+pub mod speculative_tests;
+#[cfg(test)]
+pub mod timeout_tests;
+#[cfg(test)]
+pub mod worktree_tests;
+
+/// `count` commits in a line, none of them tested yet. Hashes count up from `c0ffee0…0`, like the ones in a bisect log.
+#[cfg(test)]
+pub fn untested_commits(count: usize) -> Vec<CommitState> {
+    (0..count)
+        .map(|i| CommitState::new(format!("c0ffee{i:034}"), CommitMeta::default()))
+        .collect()
+}
+
+/// `count` commits in a line, the first one good and the last one bad.
+#[cfg(test)]
+pub fn commits(count: usize) -> Vec<CommitState> {
+    let mut commits = untested_commits(count);
+    commits[0].status = Status::Good;
+    commits[count - 1].status = Status::Bad;
+    commits
+}
+
+/// Runners in order, to compare allocations regardless of the order runners were placed in.
+#[cfg(test)]
+pub fn sorted(mut runners: Vec<usize>) -> Vec<usize> {
+    runners.sort();
+    runners
+}
+
+/// Runs `git` in `repo` as a test user. Panics if it fails, returns what it printed.
+#[cfg(test)]
+pub fn git(repo: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .arg("-c")
+        .arg("user.name=test")
+        .arg("-c")
+        .arg("user.email=test@example.com")
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "git {args:?} failed");
+    String::from_utf8(output.stdout).unwrap()
+}
@@ -0,0 +1,68 @@
+use crate::{
+    alloc::{Allocator, ProbabilisticAllocator},
+    CommitState, Status,
+};
+
+fn commits(weights: &[f64]) -> Vec<CommitState> {
+    weights
+        .iter()
+        .enumerate()
+        .map(|(i, weight)| CommitState {
+            hash: i.to_string(),
+            status: Status::Unknown,
+            weight: *weight,
+            meta: Default::default(),
+            parents: Default::default(),
+        })
+        .collect()
+}
+
+#[test]
+fn uniform_prior_bisects() {
+    let commits = commits(&[0.1; 9]);
+
+    let allocated = ProbabilisticAllocator::alloc_runners(1, &[], (0, &commits), false);
+
+    // 9 commits plus the known-bad commit after them: 5 candidates on each side
+    assert_eq!(allocated, vec![4]);
+}
+
+#[test]
+fn skewed_prior_moves_runner_towards_mass() {
+    let mut weights = vec![0.01; 10];
+    weights[7] = 0.9;
+    let commits = commits(&weights);
+
+    let allocated = ProbabilisticAllocator::alloc_runners(1, &[], (0, &commits), false);
+
+    // Testing either side of the likely commit gains the most information
+    assert!(
+        allocated == vec![6] || allocated == vec![7],
+        "Runner not placed next to the likely commit: {allocated:?}"
+    );
+}
+
+#[test]
+fn skipped_commits_are_not_retested() {
+    let mut commits = commits(&[0.1; 9]);
+    commits[4].status = Status::Skip;
+
+    let allocated = ProbabilisticAllocator::alloc_runners(1, &[], (0, &commits), false);
+
+    assert_eq!(allocated.len(), 1);
+    assert!(!allocated.contains(&4));
+}
+
+#[test]
+fn existing_runners_split_the_range() {
+    let commits = commits(&[0.1; 9]);
+
+    let allocated = ProbabilisticAllocator::alloc_runners(2, &[4], (0, &commits), false);
+
+    // The new runner splits one of the halves left by the existing runner
+    assert_eq!(allocated.len(), 1);
+    assert!(
+        [1, 2, 6, 7].contains(&allocated[0]),
+        "Runner not placed in the middle of a half: {allocated:?}"
+    );
+}
@@ -0,0 +1,109 @@
+use std::{
+    os::unix::process::CommandExt,
+    process::Command,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    reaper::{Event, Reaped, Reaper},
+    shell::{kill_process_group, process_group_exists},
+};
+
+fn sh(script: &str) -> std::process::Child {
+    Command::new("sh").arg("-c").arg(script).spawn().unwrap()
+}
+
+fn next_exit(reaper: &Reaper, deadline: Option<Instant>) -> Option<Reaped> {
+    match reaper.next(deadline)? {
+        Event::Exited(reaped) => Some(reaped),
+        Event::Signal(signal) => panic!("Unexpected signal {signal}"),
+    }
+}
+
+#[test]
+fn reports_in_exit_order() {
+    let mut reaper = Reaper::new();
+    let slow = reaper.watch(sh("sleep 0.5; exit 3"));
+    let fast = reaper.watch(sh("exit 1"));
+
+    let first = next_exit(&reaper, None).unwrap();
+    assert_eq!(first.id, fast);
+    assert_eq!(first.status.unwrap().code(), Some(1));
+
+    let second = next_exit(&reaper, None).unwrap();
+    assert_eq!(second.id, slow);
+    assert_eq!(second.status.unwrap().code(), Some(3));
+}
+
+#[test]
+fn gives_up_at_deadline() {
+    let mut reaper = Reaper::new();
+    let id = reaper.watch(sh("sleep 0.5"));
+
+    let start = Instant::now();
+    assert!(next_exit(&reaper, Some(start + Duration::from_millis(50))).is_none());
+    assert!(start.elapsed() < Duration::from_millis(500));
+
+    assert_eq!(next_exit(&reaper, None).unwrap().id, id);
+}
+
+#[test]
+fn killing_the_group_stops_grandchildren() {
+    let pid_file = std::env::temp_dir().join(format!("biasect-pgid-{}", std::process::id()));
+    let child = Command::new("sh")
+        .arg("-c")
+        // Ignore SIGTERM in the wrapper, like a script that doesn't clean up after itself
+        .arg(format!(
+            "trap '' TERM; sleep 30 & echo $! > {}; wait",
+            pid_file.display()
+        ))
+        .process_group(0)
+        .spawn()
+        .unwrap();
+    let pid = child.id();
+    let mut reaper = Reaper::new();
+    let id = reaper.watch(child);
+
+    while std::fs::read_to_string(&pid_file).map_or(true, |x| !x.ends_with('\n')) {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    let grandchild = std::fs::read_to_string(&pid_file)
+        .unwrap()
+        .trim()
+        .parse::<libc::pid_t>()
+        .unwrap();
+
+    kill_process_group(pid, libc::SIGTERM).unwrap();
+    // The wrapper ignores SIGTERM
+    assert!(next_exit(&reaper, Some(Instant::now() + Duration::from_millis(200))).is_none());
+
+    kill_process_group(pid, libc::SIGKILL).unwrap();
+    assert_eq!(next_exit(&reaper, None).unwrap().id, id);
+    // The grandchild got SIGTERM along with the wrapper. Once orphaned it's init's to reap, so it may linger as a zombie.
+    std::thread::sleep(Duration::from_millis(100));
+    let stat = std::fs::read_to_string(format!("/proc/{grandchild}/stat")).unwrap_or_default();
+    assert!(stat.is_empty() || stat.contains(") Z "), "{stat}");
+
+    // Killing a group that's gone is fine
+    kill_process_group(pid, libc::SIGKILL).unwrap();
+    std::fs::remove_file(pid_file).unwrap();
+}
+
+#[test]
+fn process_group_is_gone_once_reaped() {
+    let child = Command::new("sh")
+        .arg("-c")
+        .arg("read line")
+        .stdin(std::process::Stdio::piped())
+        .process_group(0)
+        .spawn()
+        .unwrap();
+    let pid = child.id();
+    let mut reaper = Reaper::new();
+    reaper.watch(child);
+    assert!(process_group_exists(pid).unwrap());
+
+    kill_process_group(pid, libc::SIGKILL).unwrap();
+    next_exit(&reaper, None).unwrap();
+    assert!(!process_group_exists(pid).unwrap());
+}
@@ -0,0 +1,162 @@
+use std::{env, fs, process};
+
+use crate::{
+    alloc::{
+        get_range, init, mark_known, resume, step, Allocator, BasicAllocator, DumbAllocator,
+        KAryAllocator, ProbabilisticAllocator, RuntimeAllocator, SpeculativeAllocator,
+        WeightedAllocator,
+    },
+    persist::{load_state, remove_state, save_state, state_path},
+    shell::parse_bisect_log,
+    tests::untested_commits,
+    Status,
+};
+
+const BISECT_LOG: &str = "\
+# bad: [c0ffee0000000000000000000000000000000009] Break everything
+# good: [c0ffee0000000000000000000000000000000000] Initial commit
+git bisect start 'main' 'v1.0'
+# skip: [c0ffee0000000000000000000000000000000004] Broken build
+git bisect skip c0ffee0000000000000000000000000000000004
+# good: [c0ffee0000000000000000000000000000000003] Refactor
+git bisect good c0ffee0000000000000000000000000000000003
+# first bad commit: [c0ffee0000000000000000000000000000000009] Break everything
+";
+
+#[test]
+fn bisect_log_parsing() {
+    let log = parse_bisect_log(BISECT_LOG);
+
+    assert_eq!(
+        log,
+        vec![
+            (
+                Status::Bad,
+                "c0ffee0000000000000000000000000000000009".to_string()
+            ),
+            (
+                Status::Good,
+                "c0ffee0000000000000000000000000000000000".to_string()
+            ),
+            (
+                Status::Skip,
+                "c0ffee0000000000000000000000000000000004".to_string()
+            ),
+            (
+                Status::Good,
+                "c0ffee0000000000000000000000000000000003".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn resume_applies_bisect_log() {
+    let state = init::<ProbabilisticAllocator>(untested_commits(10), 2, false);
+    let state = resume::<ProbabilisticAllocator>(state, &parse_bisect_log(BISECT_LOG), 2);
+
+    assert_eq!(state.commits[3].status, Status::Good);
+    assert_eq!(state.commits[4].status, Status::Skip);
+    assert_eq!(state.commits[9].status, Status::Bad);
+    assert_eq!(get_range(&state.commits).0, 4);
+
+    // Only unknown commits in the remaining range are scheduled. The skipped commit isn't retested.
+    assert_eq!(state.runners.commits.len(), 2);
+    assert!(
+        state.runners.commits.iter().all(|x| (5..9).contains(x)),
+        "Runner scheduled outside of the remaining range: {:?}",
+        state.runners.commits
+    );
+}
+
+#[test]
+fn resume_keeps_in_flight_runners() {
+    let state = init::<BasicAllocator>(untested_commits(100), 4, false);
+    let (state, _, _) =
+        step::<BasicAllocator>(&state, Status::Good, state.runners.commits[0], 1.0, 1.0);
+    let in_flight = state.runners.commits.clone();
+
+    let resumed = resume::<BasicAllocator>(state, &[], 4);
+
+    assert_eq!(resumed.runners.commits, in_flight);
+}
+
+/// Resume a single runner that was validating a bookend when interrupted.
+fn resume_one_runner<F: Allocator>() {
+    let state = init::<F>(untested_commits(10), 1, true);
+    let in_flight = state.runners.commits.clone();
+    assert_eq!(in_flight.len(), 1);
+
+    let resumed = resume::<F>(state, &[], 1);
+
+    assert_eq!(resumed.runners.commits, in_flight);
+}
+
+#[test]
+fn resume_one_runner_with_bookends() {
+    resume_one_runner::<DumbAllocator>();
+    resume_one_runner::<BasicAllocator>();
+    resume_one_runner::<WeightedAllocator>();
+    resume_one_runner::<ProbabilisticAllocator>();
+    resume_one_runner::<RuntimeAllocator>();
+    resume_one_runner::<SpeculativeAllocator>();
+    resume_one_runner::<KAryAllocator>();
+}
+
+#[test]
+fn state_round_trip() {
+    let dir = env::temp_dir().join(format!("biasect-test-{}", process::id()));
+    let path = state_path(&dir);
+
+    let state = init::<BasicAllocator>(untested_commits(10), 2, true);
+    let (state, _, _) = step::<BasicAllocator>(&state, Status::Bad, 9, 1.5, 1.5);
+    save_state(&state, &path).unwrap();
+
+    let loaded = load_state(&path).unwrap();
+    assert_eq!(loaded.runtime_samples, state.runtime_samples);
+    assert_eq!(loaded.runners.commits, state.runners.commits);
+    assert_eq!(
+        loaded.commits.iter().map(|c| c.status).collect::<Vec<_>>(),
+        state.commits.iter().map(|c| c.status).collect::<Vec<_>>()
+    );
+
+    remove_state(&path).unwrap();
+    assert!(load_state(&path).is_err());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn init_honors_bisect_log() {
+    let mut commits = untested_commits(10);
+    mark_known(&mut commits, &parse_bisect_log(BISECT_LOG), false);
+
+    let state = init::<BasicAllocator>(commits, 4, false);
+
+    assert_eq!(state.commits[0].status, Status::Good);
+    assert_eq!(state.commits[3].status, Status::Good);
+    assert_eq!(state.commits[4].status, Status::Skip);
+    assert_eq!(state.commits[9].status, Status::Bad);
+    assert_eq!(state.runners.commits.len(), 4);
+    assert!(
+        state
+            .runners
+            .commits
+            .iter()
+            .all(|x| *x > 3 && state.commits[*x].status == Status::Unknown),
+        "Runner scheduled on a commit decided by the bisect log: {:?}",
+        state.runners.commits
+    );
+}
+
+#[test]
+fn bisect_log_does_not_validate_bookends() {
+    let mut commits = untested_commits(10);
+    mark_known(&mut commits, &parse_bisect_log(BISECT_LOG), true);
+
+    let state = init::<BasicAllocator>(commits, 2, true);
+
+    assert_eq!(state.commits[0].status, Status::Unknown);
+    assert_eq!(state.commits[3].status, Status::Good);
+    assert_eq!(state.commits[9].status, Status::Unknown);
+    assert!(state.runners.commits.contains(&9));
+}
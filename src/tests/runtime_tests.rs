@@ -0,0 +1,66 @@
+use crate::{
+    alloc::{expected_finish, Allocator, RuntimeAllocator, Timing},
+    tests::untested_commits,
+};
+
+#[test]
+fn expected_finish_given_elapsed_time() {
+    let samples = [10.0, 20.0, 60.0];
+
+    assert_eq!(expected_finish(&[], 0.0, 5.0), None);
+    assert_eq!(expected_finish(&samples, 0.0, 0.0), Some(30.0));
+    // Running for 15 already rules out the 10
+    assert_eq!(expected_finish(&samples, 100.0, 115.0), Some(140.0));
+    // Longer than anything so far
+    assert_eq!(expected_finish(&samples, 0.0, 70.0), Some(70.0));
+}
+
+#[test]
+fn speculates_around_runners_finishing_first() {
+    let commits = untested_commits(99);
+    let samples = [10.0, 10.0, 10.0, 100.0];
+
+    // The runner on 49 is expected to finish at 62.5, before a new runner at 67.5
+    let allocated = RuntimeAllocator::alloc_timed(
+        2,
+        &[49],
+        (0, &commits),
+        false,
+        &Timing {
+            runtime_samples: &samples,
+            start_times: &[30.0],
+            now: 35.0,
+        },
+    );
+    assert!(
+        allocated
+            .iter()
+            .all(|x| (20..30).contains(x) || (70..80).contains(x)),
+        "Expected runners in either half, got {allocated:?}"
+    );
+}
+
+#[test]
+fn doesnt_wait_on_slow_runners() {
+    let commits = untested_commits(99);
+    let samples = [10.0, 10.0, 10.0, 100.0];
+
+    // The runner on 49 has run longer than 10, so it's expected to take 100 and finish after a new runner at 52.5.
+    // Counting on its result would put the new runner in one of the halves.
+    let allocated = RuntimeAllocator::alloc_timed(
+        2,
+        &[49],
+        (0, &commits),
+        false,
+        &Timing {
+            runtime_samples: &samples,
+            start_times: &[0.0],
+            now: 20.0,
+        },
+    );
+    assert_eq!(allocated.len(), 1);
+    assert!(
+        (45..55).contains(&allocated[0]),
+        "Expected a runner next to the slow one, got {allocated:?}"
+    );
+}
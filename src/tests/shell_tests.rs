@@ -0,0 +1,129 @@
+use std::{env, ffi::OsStr, fs, os::unix::ffi::OsStrExt, path::PathBuf, process};
+
+use crate::{
+    shell::{
+        bisect_report, get_bisect_log, get_commits, get_git_dir, parse_range_meta, ShellError,
+    },
+    Status,
+};
+
+fn dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("biasect-{name}-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn not_a_repo() {
+    let dir = dir("not-a-repo");
+
+    assert!(matches!(get_git_dir(&dir), Err(ShellError::NotARepo(path)) if path == dir));
+    assert!(matches!(
+        get_git_dir(&dir.join("missing")),
+        Err(ShellError::NotARepo(_))
+    ));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn no_bisect_in_progress() {
+    let dir = dir("no-bisect");
+    process::Command::new("git")
+        .arg("init")
+        .arg("-q")
+        .arg(&dir)
+        .status()
+        .unwrap();
+
+    assert!(matches!(get_commits(&dir), Err(ShellError::NoBisect)));
+    assert!(matches!(get_bisect_log(&dir), Err(ShellError::NoBisect)));
+    assert!(matches!(
+        bisect_report(&dir, &Status::Good, &"HEAD".to_string()),
+        Err(ShellError::NoBisect)
+    ));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn non_utf8_path() {
+    let path = PathBuf::from(OsStr::from_bytes(b"/tmp/biasect-\xff"));
+    assert!(matches!(
+        get_git_dir(&path),
+        Err(ShellError::NonUtf8Path(_))
+    ));
+}
+
+#[test]
+fn messages_include_stderr() {
+    let err = ShellError::CommandFailed {
+        command: "git bisect good deadbeef".to_string(),
+        stderr: "fatal: bad revision 'deadbeef'\n".to_string(),
+    };
+    assert_eq!(
+        err.to_string(),
+        "`git bisect good deadbeef` failed:\nfatal: bad revision 'deadbeef'"
+    );
+    assert!(String::from(ShellError::NoBisect).contains("git bisect start"));
+}
+
+#[test]
+fn failed_report_includes_stderr() {
+    let dir = dir("failed-report");
+    let git = |args: &[&str]| {
+        let status = process::Command::new("git")
+            .arg("-C")
+            .arg(&dir)
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {args:?} failed");
+    };
+    git(&["init", "-q"]);
+    git(&["commit", "-q", "--allow-empty", "-m", "Good"]);
+    git(&["commit", "-q", "--allow-empty", "-m", "Bad"]);
+    git(&["bisect", "start", "HEAD", "HEAD~"]);
+
+    match bisect_report(&dir, &Status::Good, &"deadbeef".to_string()) {
+        Err(ShellError::CommandFailed { command, stderr }) => {
+            assert!(command.ends_with("bisect good deadbeef"), "{command}");
+            assert!(stderr.contains("deadbeef"), "{stderr}");
+        }
+        res => panic!("Expected the report to fail, got {res:?}"),
+    }
+    assert!(matches!(
+        bisect_report(&dir, &Status::Unknown, &"deadbeef".to_string()),
+        Err(ShellError::UnknownStatus(_))
+    ));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn range_meta_parsing() {
+    let log = "\0c0ffee2\0Jane <jane@example.com>\0Merge branch 'fix'\0\
+        \0c0ffee1\0Jane <jane@example.com>\0\0\
+        \nsrc/main.rs\0README.md\0\
+        \0c0ffee0\0Bot [bot] <bot@example.com>\0Update deps\0\
+        \nCargo.lock\0";
+
+    let metas = parse_range_meta(log.as_bytes()).unwrap();
+
+    assert_eq!(metas.len(), 3);
+    // Merges list no files
+    assert!(metas["c0ffee2"].files.is_empty());
+    assert_eq!(
+        metas["c0ffee2"].subject.as_deref(),
+        Some("Merge branch 'fix'")
+    );
+    assert_eq!(metas["c0ffee1"].files, vec!["src/main.rs", "README.md"]);
+    assert_eq!(metas["c0ffee1"].subject.as_deref(), Some(""));
+    assert_eq!(
+        metas["c0ffee0"].author.as_deref(),
+        Some("Bot [bot] <bot@example.com>")
+    );
+    assert_eq!(metas["c0ffee0"].files, vec!["Cargo.lock"]);
+}
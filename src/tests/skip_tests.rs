@@ -0,0 +1,106 @@
+use crate::{
+    alloc::{
+        init, skip_shadow, skipped_suspects, step, Allocator, BasicAllocator, DumbAllocator,
+        KAryAllocator, ProbabilisticAllocator, SpeculativeAllocator, WeightedAllocator,
+    },
+    tests::commits,
+    Status,
+};
+
+#[test]
+fn shadow_grows_with_skipped_runs() {
+    let mut commits = commits(17);
+    for x in [4, 10, 11, 12] {
+        commits[x].status = Status::Skip;
+    }
+
+    let shadowed = skip_shadow(&commits)
+        .into_iter()
+        .enumerate()
+        .filter(|(_, shadowed)| *shadowed)
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    assert_eq!(shadowed, vec![3, 5, 7, 8, 9, 13, 14, 15]);
+}
+
+/// Where a single runner goes between a good and a bad commit, with commit 8 of 17 skipped.
+fn next_to_skipped<F: Allocator>() -> Vec<usize> {
+    let mut commits = commits(17);
+    commits[8].status = Status::Skip;
+    F::alloc_runners(1, &[], (1, &commits[1..16]), false)
+}
+
+#[test]
+fn avoids_neighbours_of_skipped_commits() {
+    for allocated in [
+        next_to_skipped::<BasicAllocator>(),
+        next_to_skipped::<WeightedAllocator>(),
+        next_to_skipped::<ProbabilisticAllocator>(),
+        next_to_skipped::<SpeculativeAllocator>(),
+        next_to_skipped::<KAryAllocator>(),
+    ] {
+        assert_eq!(allocated.len(), 1);
+        assert!(
+            !(7..=9).contains(&allocated[0]),
+            "Allocated {allocated:?} next to skipped commit 8"
+        );
+    }
+}
+
+/// Where two runners go when only skipped commits are left.
+fn only_skipped<F: Allocator>() -> Vec<usize> {
+    let mut commits = commits(5);
+    for commit in &mut commits[1..4] {
+        commit.status = Status::Skip;
+    }
+    F::alloc_runners(2, &[], (1, &commits[1..4]), false)
+}
+
+#[test]
+fn never_retests_skipped_commits() {
+    assert!(only_skipped::<DumbAllocator>().is_empty());
+    assert!(only_skipped::<BasicAllocator>().is_empty());
+    assert!(only_skipped::<WeightedAllocator>().is_empty());
+    assert!(only_skipped::<ProbabilisticAllocator>().is_empty());
+    assert!(only_skipped::<SpeculativeAllocator>().is_empty());
+    assert!(only_skipped::<KAryAllocator>().is_empty());
+}
+
+#[test]
+fn lists_skipped_suspects() {
+    let mut commits = commits(5);
+    commits[2].status = Status::Skip;
+    commits[3].status = Status::Skip;
+
+    // Commit 1 may still be the first bad commit
+    assert!(skipped_suspects(&commits).is_empty());
+
+    commits[1].status = Status::Good;
+    assert_eq!(skipped_suspects(&commits), vec![2, 3, 4]);
+
+    commits[3].status = Status::Good;
+    assert!(skipped_suspects(&commits).is_empty());
+}
+
+#[test]
+fn bisects_up_to_skipped_commits() {
+    // Commits 40 to 49 don't build, and the first bad commit is among them
+    let mut state = init::<BasicAllocator>(commits(100), 1, false);
+    let mut time = 0.0;
+    while let Some(commit) = state.runners.commits.first().copied() {
+        let status = match commit {
+            40..=49 => Status::Skip,
+            50.. => Status::Bad,
+            _ => Status::Good,
+        };
+
+        time += 1.0;
+        (state, _, _) = step::<BasicAllocator>(&state, status, commit, 1.0, time);
+        assert!(time < 100.0, "Bisection didn't finish");
+    }
+
+    assert_eq!(
+        skipped_suspects(&state.commits),
+        (40..=50).collect::<Vec<_>>()
+    );
+}
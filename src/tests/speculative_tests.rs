@@ -0,0 +1,69 @@
+use crate::{
+    alloc::{step, Allocator, SpeculativeAllocator},
+    tests::{commits, sorted},
+    Runners, State, Status,
+};
+
+#[test]
+fn bisects_breadth_first() {
+    let commits = commits(17);
+
+    let allocated = SpeculativeAllocator::alloc_runners(7, &[], (1, &commits[1..16]), false);
+    assert_eq!(sorted(allocated), vec![2, 4, 6, 8, 10, 12, 14]);
+
+    let allocated = SpeculativeAllocator::alloc_initial(&commits, 3, false);
+    assert_eq!(sorted(allocated), vec![4, 8, 12]);
+}
+
+#[test]
+fn speculates_on_both_sides_of_runners_in_flight() {
+    let commits = commits(17);
+
+    let allocated = SpeculativeAllocator::alloc_runners(3, &[8], (1, &commits[1..16]), false);
+    assert_eq!(sorted(allocated), vec![4, 12]);
+
+    // Off-center runners in flight are split around, too
+    let allocated = SpeculativeAllocator::alloc_runners(3, &[3], (1, &commits[1..16]), false);
+    assert_eq!(sorted(allocated), vec![1, 9]);
+}
+
+#[test]
+fn survivors_are_the_next_split() {
+    let state = State {
+        runtime_samples: vec![],
+        commits: commits(17),
+        runners: Runners {
+            commits: vec![8, 4, 12],
+            start_times: vec![0.0; 3],
+            total: 3,
+        },
+        check_bookends: false,
+        flaky: None,
+    };
+
+    // Whichever way 8 goes, the runner left is in the middle of what's left, and the free ones split both sides of it
+    let (stepped, invalidated, new_runners) =
+        step::<SpeculativeAllocator>(&state, Status::Good, 8, 1.0, 1.0);
+    assert_eq!(invalidated, [4, 8].into());
+    assert_eq!(sorted(new_runners), vec![10, 14]);
+    assert_eq!(stepped.runners.commits[0], 12);
+
+    let (stepped, invalidated, new_runners) =
+        step::<SpeculativeAllocator>(&state, Status::Bad, 8, 1.0, 1.0);
+    assert_eq!(invalidated, [8, 12].into());
+    assert_eq!(sorted(new_runners), vec![2, 6]);
+    assert_eq!(stepped.runners.commits[0], 4);
+}
+
+#[test]
+fn skips_known_commits() {
+    let mut commits = commits(17);
+    commits[8].status = Status::Skip;
+
+    // Nor the commits next to it, which are likely to be skipped too
+    let allocated = SpeculativeAllocator::alloc_runners(1, &[], (1, &commits[1..16]), false);
+    assert!(
+        allocated == vec![6] || allocated == vec![10],
+        "{allocated:?}"
+    );
+}
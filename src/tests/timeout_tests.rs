@@ -0,0 +1,43 @@
+use std::time::Duration;
+
+use crate::timeout::Timeout;
+
+#[test]
+fn parsing() {
+    assert_eq!(
+        "300".parse::<Timeout>(),
+        Ok(Timeout::Fixed(Duration::from_secs(300)))
+    );
+    assert_eq!(
+        "1.5s".parse::<Timeout>(),
+        Ok(Timeout::Fixed(Duration::from_millis(1500)))
+    );
+    assert_eq!("3x".parse::<Timeout>(), Ok(Timeout::Learned(3.0)));
+
+    assert!("".parse::<Timeout>().is_err());
+    assert!("0".parse::<Timeout>().is_err());
+    assert!("-3x".parse::<Timeout>().is_err());
+    assert!("5m".parse::<Timeout>().is_err());
+}
+
+#[test]
+fn fixed_deadline() {
+    let timeout = Timeout::Fixed(Duration::from_secs(10));
+    assert_eq!(timeout.deadline(&[]), Some(Duration::from_secs(10)));
+    assert_eq!(timeout.deadline(&[100.0]), Some(Duration::from_secs(10)));
+}
+
+#[test]
+fn learned_deadline() {
+    let timeout = Timeout::Learned(3.0);
+    // Nothing to learn from yet
+    assert_eq!(timeout.deadline(&[]), None);
+    assert_eq!(
+        timeout.deadline(&[1.0, 100.0, 2.0]),
+        Some(Duration::from_secs(6))
+    );
+    assert_eq!(
+        timeout.deadline(&[1.0, 3.0, 100.0, 2.0]),
+        Some(Duration::from_secs_f64(7.5))
+    );
+}
@@ -0,0 +1,144 @@
+use std::{env, fs, path::Path, process, process::Command};
+
+use crate::{
+    tests::git,
+    worktree::{Clean, Worktrees},
+};
+
+fn repo(name: &str) -> std::path::PathBuf {
+    let repo = env::temp_dir().join(format!("biasect-{name}-{}", process::id()));
+    fs::create_dir_all(&repo).unwrap();
+    git(&repo, &["init", "-q"]);
+    git(
+        &repo,
+        &["commit", "-q", "--allow-empty", "-m", "Initial commit"],
+    );
+    repo
+}
+
+fn sh(dir: &Path, command: &str) {
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .current_dir(dir)
+        .status()
+        .unwrap();
+    assert!(status.success(), "`{command}` failed");
+}
+
+#[test]
+fn removes_checked_out_and_empty_worktrees() {
+    let repo = repo("worktrees");
+    let mut worktrees = Worktrees::new(&repo, Clean::None);
+
+    let (checked_out, checkout) = worktrees.acquire("HEAD").unwrap();
+    let (empty, _) = worktrees.acquire("HEAD").unwrap();
+    assert_ne!(checked_out, empty);
+    sh(&checked_out, &checkout);
+    assert!(git(&repo, &["worktree", "list"]).contains(checked_out.to_str().unwrap()));
+    assert_eq!(worktrees.paths().len(), 2);
+
+    worktrees.remove(&checked_out).unwrap();
+    assert!(!checked_out.exists());
+    assert!(!git(&repo, &["worktree", "list"]).contains(checked_out.to_str().unwrap()));
+
+    // A runner killed before it checked out anything
+    worktrees.remove_all().unwrap();
+    assert!(!empty.exists());
+    assert!(worktrees.paths().is_empty());
+
+    fs::remove_dir_all(repo).unwrap();
+}
+
+#[test]
+fn removes_worktrees_left_when_dropped() {
+    let repo = repo("dropped");
+    let mut worktrees = Worktrees::new(&repo, Clean::None);
+
+    let (path, checkout) = worktrees.acquire("HEAD").unwrap();
+    sh(&path, &checkout);
+    drop(worktrees);
+    assert!(!path.exists());
+    assert!(!git(&repo, &["worktree", "list"]).contains(path.to_str().unwrap()));
+
+    fs::remove_dir_all(repo).unwrap();
+}
+
+fn reuse(clean: Clean) -> (bool, bool) {
+    let repo = repo(&format!("reuse-{clean:?}"));
+    fs::write(repo.join(".gitignore"), "target/\n").unwrap();
+    git(&repo, &["add", ".gitignore"]);
+    git(
+        &repo,
+        &[
+            "-c",
+            "user.name=test",
+            "-c",
+            "user.email=test@example.com",
+            "commit",
+            "-q",
+            "-m",
+            "Ignore build artifacts",
+        ],
+    );
+    let mut worktrees = Worktrees::new(&repo, clean);
+    // Runners get hashes. `HEAD` would mean the reused worktree's own HEAD.
+    let old = git(&repo, &["rev-parse", "HEAD~"]);
+    let new = git(&repo, &["rev-parse", "HEAD"]);
+
+    let (path, checkout) = worktrees.acquire(old.trim()).unwrap();
+    sh(&path, &checkout);
+    sh(&path, "mkdir target && touch target/artifact untracked");
+    worktrees.release(&path);
+
+    let (reused, checkout) = worktrees.acquire(new.trim()).unwrap();
+    assert_eq!(reused, path);
+    sh(&reused, &checkout);
+    assert!(reused.join(".gitignore").exists());
+    let kept = (
+        reused.join("target/artifact").exists(),
+        reused.join("untracked").exists(),
+    );
+
+    worktrees.remove_all().unwrap();
+    fs::remove_dir_all(repo).unwrap();
+    kept
+}
+
+#[test]
+fn reused_worktrees_follow_clean_policy() {
+    assert_eq!(reuse(Clean::None), (true, true));
+    assert_eq!(reuse(Clean::Untracked), (true, false));
+    assert_eq!(reuse(Clean::All), (false, false));
+}
+
+#[test]
+fn recycles_worktrees_of_killed_runners() {
+    let repo = repo("recycle");
+    let mut worktrees = Worktrees::new(&repo, Clean::None);
+
+    // Killed while running the script, with build artifacts and a tracked file half written
+    let (path, checkout) = worktrees.acquire("HEAD").unwrap();
+    sh(&path, &checkout);
+    sh(&path, "touch artifact tracked && git add tracked");
+    worktrees.recycle(&path).unwrap();
+    assert!(path.join("artifact").exists());
+    assert!(!path.join("tracked").exists());
+    assert_eq!(worktrees.acquire("HEAD").unwrap().0, path);
+
+    // Killed in the middle of a checkout, which left the index locked
+    let git_dir = git(&path, &["rev-parse", "--absolute-git-dir"]);
+    fs::write(Path::new(git_dir.trim()).join("index.lock"), "").unwrap();
+    worktrees.recycle(&path).unwrap();
+    assert!(!path.exists());
+
+    // Killed before it checked anything out
+    let (empty, _) = worktrees.acquire("HEAD").unwrap();
+    worktrees.recycle(&empty).unwrap();
+    assert_eq!(worktrees.acquire("HEAD").unwrap().0, empty);
+
+    worktrees.remove_all().unwrap();
+    assert!(!empty.exists());
+
+    fs::remove_dir_all(repo).unwrap();
+}
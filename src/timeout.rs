@@ -0,0 +1,61 @@
+/// Deadlines for runners, so a hung script can't stall the bisection.
+use std::{str::FromStr, time::Duration};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Timeout {
+    /// Kill runners after a fixed time.
+    Fixed(Duration),
+    /// Kill runners after a multiple of the median runtime so far.
+    Learned(f64),
+}
+
+impl FromStr for Timeout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "Invalid timeout `{s}`. Expected seconds (eg. `300`) or a multiple of the median runtime (eg. `3x`)"
+            )
+        };
+        let parse = |x: &str| match x.parse::<f64>() {
+            Ok(x) if x.is_finite() && x > 0.0 => Ok(x),
+            _ => Err(invalid()),
+        };
+
+        match s.strip_suffix('x') {
+            Some(multiple) => Ok(Timeout::Learned(parse(multiple)?)),
+            None => Ok(Timeout::Fixed(Duration::from_secs_f64(parse(
+                s.strip_suffix('s').unwrap_or(s),
+            )?))),
+        }
+    }
+}
+
+fn median(samples: &[f64]) -> Option<f64> {
+    if samples.is_empty() {
+        return None;
+    }
+
+    let mut samples = samples.to_vec();
+    samples.sort_by(f64::total_cmp);
+
+    let mid = samples.len() / 2;
+    if samples.len().is_multiple_of(2) {
+        Some((samples[mid - 1] + samples[mid]) / 2.0)
+    } else {
+        Some(samples[mid])
+    }
+}
+
+impl Timeout {
+    /// How long a runner may take. A learned timeout has no deadline until a runner has finished.
+    pub fn deadline(&self, runtime_samples: &[f64]) -> Option<Duration> {
+        match self {
+            Timeout::Fixed(deadline) => Some(*deadline),
+            Timeout::Learned(multiple) => {
+                median(runtime_samples).map(|median| Duration::from_secs_f64(median * multiple))
+            }
+        }
+    }
+}
@@ -0,0 +1,175 @@
+/// Temporary worktrees of the runners. Reused from one runner to the next, so build caches survive between steps.
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+    str::FromStr,
+};
+
+use crate::shell::{
+    worktree_add, worktree_prune, worktree_remove, worktree_reset, worktree_switch_command,
+};
+
+/// What to clean up before reusing a worktree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Clean {
+    /// Keep untracked and ignored files, such as build artifacts.
+    #[default]
+    None,
+    /// Remove untracked files, but keep ignored ones.
+    Untracked,
+    /// Remove untracked and ignored files. As good as a fresh worktree.
+    All,
+}
+
+impl Clean {
+    /// Flags for `git clean`, if anything is to be cleaned.
+    fn flags(&self) -> Option<&'static str> {
+        match self {
+            Clean::None => None,
+            Clean::Untracked => Some("-fd"),
+            Clean::All => Some("-fdx"),
+        }
+    }
+}
+
+impl FromStr for Clean {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Clean::None),
+            "untracked" => Ok(Clean::Untracked),
+            "all" => Ok(Clean::All),
+            _ => Err(format!(
+                "Unknown clean policy `{s}`. Valid choices are none, untracked, all."
+            )),
+        }
+    }
+}
+
+/// Pool of worktrees. Every runner borrows one and gives it back when it's done.
+pub struct Worktrees {
+    repo_path: PathBuf,
+    clean: Clean,
+    /// Every worktree that hasn't been removed yet
+    paths: HashSet<PathBuf>,
+    /// Checked out worktrees no runner is using
+    free: Vec<PathBuf>,
+}
+
+impl Worktrees {
+    pub fn new(repo_path: &Path, clean: Clean) -> Self {
+        Worktrees {
+            repo_path: repo_path.to_path_buf(),
+            clean,
+            paths: HashSet::new(),
+            free: Vec::new(),
+        }
+    }
+
+    /// Borrow a worktree for a runner testing `commit`.
+    /// Returns the worktree and the shell commands that check out `commit` in it, which the runner runs itself.
+    pub fn acquire(&mut self, commit: &str) -> Result<(PathBuf, String), String> {
+        let path = match self.free.pop() {
+            Some(path) => path,
+            None => {
+                // Added one at a time, since `git worktree add` fails on other worktrees that are half added
+                let path = self.create()?;
+                worktree_add(&self.repo_path, &path, commit)?;
+                path
+            }
+        };
+
+        let checkout = worktree_switch_command(commit, self.clean.flags());
+        Ok((path, checkout))
+    }
+
+    /// A fresh directory for a new worktree.
+    fn create(&mut self) -> Result<PathBuf, String> {
+        let tempdir_cmd = Command::new("mktemp")
+            .arg("-d")
+            .arg("-t")
+            .arg("biasect.XXXXXX")
+            .output()
+            .map_err(|err| format!("Failed to create tempdir via `mktemp -d -t biasect`: {err}"))?;
+        if !tempdir_cmd.status.success() {
+            return Err(format!(
+                "Failed to create tempdir via `mktemp -d -t biasect`: {}",
+                String::from_utf8_lossy(&tempdir_cmd.stderr).trim()
+            ));
+        }
+
+        let path = PathBuf::from(String::from_utf8_lossy(&tempdir_cmd.stdout).trim());
+        self.paths.insert(path.clone());
+
+        Ok(path)
+    }
+
+    /// Give back the worktree of a runner that finished, so the next runner can reuse it.
+    pub fn release(&mut self, path: &Path) {
+        if self.paths.contains(path) {
+            self.free.push(path.to_path_buf());
+        }
+    }
+
+    /// Give back the worktree of a runner that was killed, once everything it started is gone.
+    /// It may have been killed in the middle of a checkout, so its tracked files are reset first.
+    /// If it isn't a worktree, or the reset fails, it's removed instead.
+    pub fn recycle(&mut self, path: &Path) -> Result<(), String> {
+        // Without its own `.git`, git would find the repository of a parent directory, if any
+        if path.join(".git").exists() && worktree_reset(path).is_ok() {
+            self.release(path);
+            return Ok(());
+        }
+
+        self.remove(path)
+    }
+
+    pub fn paths(&self) -> &HashSet<PathBuf> {
+        &self.paths
+    }
+
+    /// Remove a worktree for good. Used for worktrees killed runners left in a state that can't be reused.
+    pub fn remove(&mut self, path: &Path) -> Result<(), String> {
+        if !self.paths.remove(path) {
+            return Ok(());
+        }
+        self.free.retain(|free| free != path);
+
+        if worktree_remove(&self.repo_path, path).is_ok() {
+            return Ok(());
+        }
+
+        // The runner was killed before or while checking out its worktree.
+        // Remove what's left of it, then let git forget about it.
+        match fs::remove_dir_all(path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                return Err(format!("Failed to remove `{}`: {err}", path.display()));
+            }
+            _ => (),
+        }
+        worktree_prune(&self.repo_path)?;
+
+        Ok(())
+    }
+
+    pub fn remove_all(&mut self) -> Result<(), String> {
+        for path in self.paths.clone() {
+            self.remove(&path)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Worktrees still around when the pool goes away, eg. after an early return or a panic, are removed too.
+impl Drop for Worktrees {
+    fn drop(&mut self) {
+        // Nowhere to report errors to. Whatever can't be removed is left to `git worktree prune`.
+        for path in self.paths.clone() {
+            let _ = self.remove(&path);
+        }
+    }
+}